@@ -1,12 +1,17 @@
 use std::{
+    collections::BTreeMap,
     env,
     error::Error,
+    fmt::Write,
     fs::{self, OpenOptions},
     path::{Path, PathBuf},
 };
 
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
+use unreal_mod_manager::unreal_mod_metadata::{self, Metadata};
 use unreal_mod_manager::unreal_pak::{pakversion::PakVersion, PakWriter};
 
 fn add_extension(path: &mut PathBuf, extension: &str) {
@@ -23,8 +28,124 @@ fn add_extension(path: &mut PathBuf, extension: &str) {
     }
 }
 
+#[path = "src/handlers/game_maps.rs"]
+#[allow(dead_code)]
+mod game_maps;
+
+use game_maps::MAP_PATHS;
+
+/// Mod id of the baked mod that is registered as the core mod
+const CORE_MOD_ID: &str = "CoreMod";
+
+/// Get the maps persistent actors are injected into from a mod's metadata
+fn persistent_actor_maps(metadata: &Metadata) -> Result<Vec<String>, String> {
+    match metadata.integrator.get("persistent_actor_maps") {
+        Some(maps) => serde_json::from_value(maps.clone())
+            .map_err(|e| format!("invalid persistent_actor_maps: {e}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Check a baked mod's metadata against its `priority-ModId-version_P`
+/// directory name and return the priority
+fn validate_baked_mod(dir_name: &str, metadata: &Metadata) -> Result<u16, String> {
+    if metadata.schema_version != 2 {
+        return Err(format!(
+            "unsupported schema_version {}, expected 2",
+            metadata.schema_version
+        ));
+    }
+
+    let invalid_name = || String::from("directory name must match priority-ModId-version_P");
+    let (priority, rest) = dir_name
+        .strip_suffix("_P")
+        .and_then(|e| e.split_once('-'))
+        .ok_or_else(invalid_name)?;
+    let (mod_id, version) = rest.split_once('-').ok_or_else(invalid_name)?;
+
+    if priority.len() != 3 || !priority.chars().all(|e| e.is_ascii_digit()) {
+        return Err(format!("priority {priority} must be three digits"));
+    }
+    if mod_id.is_empty() || !mod_id.chars().all(|e| e.is_ascii_alphanumeric()) {
+        return Err(format!("mod id {mod_id} must be alphanumeric"));
+    }
+    if mod_id != metadata.mod_id {
+        return Err(format!(
+            "directory mod id {mod_id} doesn't match mod_id {}",
+            metadata.mod_id
+        ));
+    }
+    if version != metadata.mod_version {
+        return Err(format!(
+            "directory version {version} doesn't match version {}",
+            metadata.mod_version
+        ));
+    }
+
+    for map in &persistent_actor_maps(metadata)? {
+        if !MAP_PATHS.contains(&map.as_str()) {
+            return Err(format!(
+                "unknown map {map} in persistent_actor_maps, expected one of {}",
+                MAP_PATHS.join(", ")
+            ));
+        }
+    }
+
+    Ok(priority.parse().unwrap())
+}
+
+/// Environment variable selecting how baked paks are compressed
+const COMPRESSION_VAR: &str = "ASTRO_BAKED_COMPRESSION";
+
+/// Compression of the entries in baked paks
+#[derive(Clone, Copy)]
+enum BakedCompression {
+    None,
+    Zlib,
+}
+
+impl BakedCompression {
+    fn from_env() -> Result<Self, String> {
+        match env::var(COMPRESSION_VAR).as_deref() {
+            Err(_) | Ok("zlib") => Ok(BakedCompression::Zlib),
+            Ok("none") => Ok(BakedCompression::None),
+            Ok(other) => Err(format!(
+                "Invalid {COMPRESSION_VAR} {other}, expected one of zlib, none"
+            )),
+        }
+    }
+
+    fn compress(self) -> bool {
+        match self {
+            BakedCompression::None => false,
+            BakedCompression::Zlib => true,
+        }
+    }
+}
+
+/// Name of the copy of the baked pak manifest next to the built artifacts
+const MANIFEST_NAME: &str = "astro_baked_manifest.json";
+
+/// Hashes of a baked pak and of the files packed into it
+#[derive(Serialize)]
+struct BakedPakManifest {
+    sha256: String,
+    files: BTreeMap<String, String>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|e| format!("{e:02x}"))
+        .collect()
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rerun-if-changed=baked");
+    println!("cargo:rerun-if-changed=src/handlers/game_maps.rs");
+    println!("cargo:rerun-if-env-changed={COMPRESSION_VAR}");
+
+    let compression = BakedCompression::from_env()?;
 
     let baked_dir = fs::read_dir("baked")?;
     let out_dir = env::var("OUT_DIR")?;
@@ -32,13 +153,26 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     fs::create_dir_all(&out_dir)?;
 
-    for path in baked_dir
+    let mut baked_paths: Vec<PathBuf> = baked_dir
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().unwrap().is_dir())
-    {
-        let path = path.path();
-        let mut pak_path = out_dir.join(path.file_name().unwrap());
+        .map(|e| e.path())
+        .collect();
+    baked_paths.sort();
+
+    let mut baked_mods = String::new();
+    let mut manifest = BTreeMap::new();
+
+    for path in baked_paths {
+        let dir_name = path.file_name().unwrap().to_str().unwrap();
+        let metadata = unreal_mod_metadata::from_slice(&fs::read(path.join("metadata.json"))?)
+            .map_err(|e| format!("Invalid metadata.json in baked mod {dir_name}: {e}"))?;
+        let priority = validate_baked_mod(dir_name, &metadata)
+            .map_err(|e| format!("Invalid baked mod {dir_name}: {e}"))?;
+
+        let mut pak_path = out_dir.join(dir_name);
         add_extension(&mut pak_path, "pak");
+        let pak_name = pak_path.file_name().unwrap().to_str().unwrap().to_owned();
 
         OpenOptions::new()
             .create(true)
@@ -46,21 +180,75 @@ fn main() -> Result<(), Box<dyn Error>> {
             .truncate(true)
             .open(&pak_path)?;
 
-        let file = OpenOptions::new().append(true).open(pak_path)?;
+        let file = OpenOptions::new().append(true).open(&pak_path)?;
 
         let mut pak = PakWriter::new(&file, PakVersion::FnameBasedCompressionMethod);
 
+        // entries are written sorted by name so that the pak is reproducible
+        let mut records = BTreeMap::new();
         for entry in WalkDir::new(&path).into_iter().map(|e| e.unwrap()) {
             if entry.file_type().is_file() {
                 let rel_path = entry.path().strip_prefix(&path).unwrap();
                 let record_name = rel_path.to_str().unwrap().replace('\\', "/");
 
-                pak.write_entry(&record_name, &fs::read(entry.path()).unwrap(), true)?;
+                records.insert(record_name, entry.into_path());
             }
         }
 
+        let mut files = BTreeMap::new();
+        for (record_name, record_path) in records {
+            let data = fs::read(record_path)?;
+            pak.write_entry(&record_name, &data, compression.compress())?;
+            files.insert(record_name, sha256_hex(&data));
+        }
+
         pak.finish_write()?;
+
+        manifest.insert(
+            pak_name.clone(),
+            BakedPakManifest {
+                sha256: sha256_hex(&fs::read(&pak_path)?),
+                files,
+            },
+        );
+
+        writeln!(
+            baked_mods,
+            "        BakedMod {{
+            data: include_bytes!(concat!(baked_path!(), {pak_name:?})),
+            mod_id: String::from({:?}),
+            filename: {pak_name:?},
+            is_core: {},
+            priority: {priority},
+        }},",
+            metadata.mod_id,
+            metadata.mod_id == CORE_MOD_ID
+        )?;
     }
 
+    let manifest_path = out_dir.join("manifest.json");
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    println!(
+        "cargo:rustc-env=ASTRO_BAKED_MANIFEST={}",
+        manifest_path.display()
+    );
+
+    // OUT_DIR is target/<profile>/build/<package>/out, the copy ends up next
+    // to the built executable
+    if let Some(profile_dir) = out_dir.ancestors().nth(4) {
+        fs::copy(&manifest_path, profile_dir.join(MANIFEST_NAME))?;
+    }
+
+    fs::write(
+        out_dir.join("baked_mods.rs"),
+        format!(
+            "pub(crate) fn baked_mods() -> Vec<BakedMod> {{
+    Vec::from([
+{baked_mods}    ])
+}}
+"
+        ),
+    )?;
+
     Ok(())
 }
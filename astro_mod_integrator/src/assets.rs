@@ -2,6 +2,9 @@ pub(crate) const ACTOR_TEMPLATE_ASSET: &[u8] = include_bytes!("../assets/ActorTe
 
 pub(crate) const ACTOR_TEMPLATE_EXPORT: &[u8] = include_bytes!("../assets/ActorTemplate.uexp");
 
+// Blocked: the alert mod NotificationActor blueprint is not part of the
+// repository, these stay disabled until its assets are added to
+// assets/alert_mod and the integrator generates the mod and warning list.
 /*pub(crate) const ALERT_MOD_NOTIFICATION_ACTOR_ASSET: &[u8] =
     include_bytes!("../assets/alert_mod/NotificationActor.uasset");
 
@@ -1,8 +1,10 @@
+use unreal_mod_manager::unreal_mod_integrator::BakedMod;
+
 macro_rules! baked_path {
     () => {
         concat!(env!("OUT_DIR"), "/baked/")
     };
 }
 
-pub(crate) const CORE_MOD: &[u8] =
-    include_bytes!(concat!(baked_path!(), "800-CoreMod-0.1.0_P.pak"));
+// generated by build.rs from the mods in the baked directory
+include!(concat!(baked_path!(), "baked_mods.rs"));
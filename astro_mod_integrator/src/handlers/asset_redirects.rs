@@ -0,0 +1,237 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    engine_version::EngineVersion, types::PackageIndex, Asset, Import,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::{game_prefix_to_absolute, get_asset_names};
+use crate::AstroIntegratorConfig;
+
+/// Redirect references to an object in the given assets to another object
+///
+/// Object paths are either `/Game/Path/Package.ObjectName`, or a package path
+/// for blueprint classes, in which case the object is the `_C` class. Asset
+/// names may end with `*` to target every asset under a path.
+#[derive(Deserialize, Serialize, Debug)]
+struct AssetRedirect {
+    pub assets: Vec<String>,
+    pub from: String,
+    pub to: String,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_asset_redirects(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    asset_redirects: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut redirects = Vec::new();
+
+    for mod_redirects in asset_redirects {
+        let mod_redirects: Vec<AssetRedirect> = serde_json::from_value(mod_redirects.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+        redirects.extend(mod_redirects);
+    }
+
+    let asset_names = get_asset_names(integrated_pak, game_paks, mod_paks);
+    let mut asset_redirects: BTreeMap<String, Vec<&AssetRedirect>> = BTreeMap::new();
+    // assets named directly rather than through a wildcard
+    let mut explicit_assets = BTreeSet::new();
+
+    for redirect in &redirects {
+        let (to_package, _) = split_object_path(&redirect.to)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid object path"))?;
+        if let Some(to_asset) = game_to_absolute(AstroIntegratorConfig::GAME_NAME, &to_package) {
+            if !asset_names.contains(&to_asset) {
+                warn!(
+                    "Redirect target {} does not exist, redirect from {} may break",
+                    redirect.to, redirect.from
+                );
+            }
+        }
+
+        for asset in &redirect.assets {
+            let targets: Vec<String> = match asset.strip_suffix('*') {
+                Some(prefix) => {
+                    let prefix = game_prefix_to_absolute(prefix).ok_or_else(|| {
+                        io::Error::new(ErrorKind::Other, format!("Invalid asset name {asset}"))
+                    })?;
+                    asset_names
+                        .iter()
+                        .filter(|e| {
+                            e.starts_with(&prefix)
+                                && (e.ends_with(".uasset") || e.ends_with(".umap"))
+                        })
+                        .cloned()
+                        .collect()
+                }
+                None => {
+                    let target = game_to_absolute(AstroIntegratorConfig::GAME_NAME, asset)
+                        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+                    explicit_assets.insert(target.clone());
+                    Vec::from([target])
+                }
+            };
+
+            for target in targets {
+                asset_redirects.entry(target).or_default().push(redirect);
+            }
+        }
+    }
+
+    for (asset_name, redirects) in &asset_redirects {
+        let explicit = explicit_assets.contains(asset_name);
+        let mut asset = match get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            asset_name,
+            EngineVersion::VER_UE4_27,
+        ) {
+            Ok(asset) => asset,
+            Err(e) if !explicit => {
+                warn!("Failed to parse {}, skipping redirects: {}", asset_name, e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut redirected = false;
+        for redirect in redirects {
+            if apply_redirect(&mut asset, redirect)? {
+                redirected = true;
+            } else if explicit {
+                warn!("{} is not referenced by {}", redirect.from, asset_name);
+            }
+        }
+
+        if redirected {
+            write_asset(integrated_pak, &asset, asset_name)
+                .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Split an object path into its package and object name
+fn split_object_path(path: &str) -> Option<(String, String)> {
+    match path.split_once('.') {
+        Some((package, object)) => Some((String::from(package), String::from(object))),
+        None => {
+            let object = Path::new(path).file_stem()?.to_str()?;
+            Some((String::from(path), String::from(object) + "_C"))
+        }
+    }
+}
+
+/// Rewrite the imports of an asset that reference `redirect.from`
+///
+/// Returns `false` without changing the asset if it doesn't reference the
+/// object.
+fn apply_redirect<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    redirect: &AssetRedirect,
+) -> Result<bool, io::Error> {
+    let invalid_path = || io::Error::new(ErrorKind::Other, "Invalid object path");
+    let (from_package, from_object) = split_object_path(&redirect.from).ok_or_else(invalid_path)?;
+    let (to_package, to_object) = split_object_path(&redirect.to).ok_or_else(invalid_path)?;
+    let from_default = String::from("Default__") + &from_object;
+
+    let find_package = |asset: &Asset<C>, package: &str| {
+        asset
+            .imports
+            .iter()
+            .position(|e| {
+                e.outer_index.index == 0
+                    && e.class_name.get_content(|e| e == "Package")
+                    && e.object_name.get_content(|e| e == package)
+            })
+            .map(|e| PackageIndex::new(-(e as i32) - 1))
+    };
+
+    let Some(from_package_import) = find_package(asset, &from_package) else {
+        return Ok(false);
+    };
+    let is_redirected_object = |import: &Import| {
+        import.outer_index == from_package_import
+            && import
+                .object_name
+                .get_content(|e| e == from_object || e == from_default)
+    };
+    let is_redirected_instance = |import: &Import| {
+        import.class_package.get_content(|e| e == from_package)
+            && import.class_name.get_content(|e| e == from_object)
+    };
+    if !asset
+        .imports
+        .iter()
+        .any(|e| is_redirected_object(e) || is_redirected_instance(e))
+    {
+        return Ok(false);
+    }
+
+    // when every object of the old package is redirected, its package import
+    // is pointed at the new package so that the old package isn't loaded as a
+    // dependency anymore, removing it would shift every import after it
+    let from_package_used = asset
+        .imports
+        .iter()
+        .any(|e| e.outer_index == from_package_import && !is_redirected_object(e));
+    if !from_package_used {
+        let fname_to_package = asset.add_fname(&to_package);
+        asset.imports[(-from_package_import.index - 1) as usize].object_name = fname_to_package;
+    }
+
+    let to_package_import = match find_package(asset, &to_package) {
+        Some(to_package_import) => to_package_import,
+        None => {
+            let package_import = Import {
+                class_package: asset.add_fname("/Script/CoreUObject"),
+                class_name: asset.add_fname("Package"),
+                outer_index: PackageIndex::new(0),
+                object_name: asset.add_fname(&to_package),
+                optional: false,
+            };
+            asset.add_import(package_import)
+        }
+    };
+
+    let fname_to_package = asset.add_fname(&to_package);
+    let fname_to_object = asset.add_fname(&to_object);
+    let fname_to_default = asset.add_fname(&(String::from("Default__") + &to_object));
+
+    for import in &mut asset.imports {
+        if is_redirected_object(import) {
+            import.object_name = match import.object_name.get_content(|e| e == from_object) {
+                true => fname_to_object.clone(),
+                false => fname_to_default.clone(),
+            };
+            import.outer_index = to_package_import;
+        }
+
+        // instances of a redirected class, such as its default object
+        if is_redirected_instance(import) {
+            import.class_package = fname_to_package.clone();
+            import.class_name = fname_to_object.clone();
+        }
+    }
+
+    Ok(true)
+}
@@ -1,11 +1,11 @@
 #![allow(clippy::io_other_error)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::{self, BufReader, ErrorKind};
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
 use std::path::Path;
 
-use log::warn;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
 use unreal_mod_manager::unreal_asset::types::PackageIndexTrait;
@@ -16,7 +16,7 @@ use unreal_mod_manager::unreal_asset::{
     exports::ExportNormalTrait,
     properties::{object_property::ObjectProperty, Property, PropertyDataTrait},
     types::PackageIndex,
-    Import,
+    Asset, Import,
 };
 use unreal_mod_manager::unreal_helpers::Guid;
 use unreal_mod_manager::unreal_mod_integrator::{
@@ -25,21 +25,42 @@ use unreal_mod_manager::unreal_mod_integrator::{
 };
 use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
 
-use super::MAP_PATHS;
+use super::{matches_wildcard, GameMap, GAME_MAPS};
 
 #[derive(Deserialize, Serialize, Debug)]
-enum BiomeType {
+pub(crate) enum BiomeType {
     Surface,
     Crust,
 }
 
+impl BiomeType {
+    /// Name of the voxel export property holding biomes of this type
+    pub(crate) fn property_name(&self) -> &'static str {
+        match self {
+            BiomeType::Surface => "SurfaceBiomes",
+            BiomeType::Crust => "CrustBiome",
+        }
+    }
+}
+
+/// A set of placement changes for planet biome layers
+///
+/// `planet_type`, `biome_name` and `layer_name` may end with `*` to match
+/// every planet, biome or layer starting with the given prefix.
 #[derive(Deserialize, Serialize, Debug)]
 struct PlacementModifier {
     pub planet_type: String,
     pub biome_type: BiomeType,
     pub biome_name: String,
     pub layer_name: String,
+    #[serde(default)]
     pub placements: Vec<String>,
+    /// Existing placements to remove, by name or path
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// Existing placements, by name or path, to replace with new placements
+    #[serde(default)]
+    pub replace: HashMap<String, String>,
 }
 
 #[allow(clippy::ptr_arg)]
@@ -59,10 +80,10 @@ pub(crate) fn handle_biome_placement_modifiers(
         biome_placement_modifiers.extend(modifiers);
     }
 
-    for map_path in MAP_PATHS {
-        if map_path == "Astro/Content/Maps/test/BasicSphereT2.umap" {
-            continue;
-        }
+    let mut applied_maps = vec![Vec::new(); biome_placement_modifiers.len()];
+
+    for game_map in &GAME_MAPS {
+        let map_path = game_map.path;
         let mut asset = get_asset(
             integrated_pak,
             game_paks,
@@ -71,176 +92,163 @@ pub(crate) fn handle_biome_placement_modifiers(
             EngineVersion::VER_UE4_27,
         )?;
 
-        let mut voxel_exports = HashMap::new();
+        let voxel_exports = get_voxel_exports(&asset)?;
+        for planet in voxel_exports
+            .keys()
+            .filter_map(|e| e.strip_suffix("Voxels"))
+            .filter(|e| !game_map.contains_planet(e))
+        {
+            warn!(
+                "Planet {} in {} is not listed for the map",
+                planet, map_path
+            );
+        }
 
-        for i in 0..asset.asset_data.exports.len() {
-            let export = &asset.asset_data.exports[i];
-            if let Some(normal_export) = export.get_normal_export() {
-                let class_index = normal_export.base_export.class_index;
-                if class_index.is_import() {
-                    let import = asset.get_import(class_index).ok_or_else(|| {
-                        io::Error::new(ErrorKind::Other, "Corrupted game installation")
-                    })?;
+        for (modifier, applied_maps) in biome_placement_modifiers.iter().zip(&mut applied_maps) {
+            let voxel_names: Vec<(&String, usize)> =
+                filter_voxel_exports(&voxel_exports, game_map, &modifier.planet_type).collect();
 
-                    if import.object_name.get_owned_content() == "VoxelVolumeComponent"
-                        && normal_export.base_export.object_name.get_owned_content()
-                            != "Default Voxel Volume"
-                    {
-                        voxel_exports
-                            .insert(normal_export.base_export.object_name.get_owned_content(), i);
-                    }
+            if voxel_names.is_empty() {
+                // planets that are not part of this map are expected to be missing
+                if game_map.has_matching_planet(&modifier.planet_type) {
+                    warn!(
+                        "Failed to find voxel export for planet {} in {}",
+                        modifier.planet_type, map_path
+                    );
                 }
+                continue;
             }
-        }
 
-        for modifier in &biome_placement_modifiers {
             let mut modifier_imports = Vec::new();
             for placement_path in &modifier.placements {
-                let placement_name = Path::new(placement_path)
-                    .file_stem()
-                    .and_then(|e| e.to_str())
-                    .ok_or_else(|| {
-                        io::Error::new(
-                            ErrorKind::Other,
-                            format!("Invalid placement {placement_path}"),
-                        )
-                    })?;
-
-                let package_import = Import {
-                    class_package: asset.add_fname("/Script/CoreUObject"),
-                    class_name: asset.add_fname("Package"),
-                    outer_index: PackageIndex::new(0),
-                    object_name: asset.add_fname(placement_path),
-                    optional: false,
-                };
-                let package_import = asset.add_import(package_import);
-
-                let modifier_import = Import {
-                    class_package: asset.add_fname("/Script/Terrain2"),
-                    class_name: asset.add_fname("ProceduralModifier"),
-                    outer_index: package_import,
-                    object_name: asset.add_fname(placement_name),
-                    optional: false,
-                };
-                let modifier_import = asset.add_import(modifier_import);
-                modifier_imports.push(modifier_import);
+                modifier_imports.push(add_placement_import(&mut asset, placement_path)?);
             }
 
-            let voxels_name = modifier.planet_type.clone() + "Voxels";
-            let export_index = voxel_exports.get(&voxels_name);
-            if export_index.is_none() {
-                warn!(
-                    "Failed to find voxel export {} for {}",
-                    voxels_name, map_path
-                );
-                continue;
+            let mut removed_imports = Vec::new();
+            for placement in &modifier.remove {
+                let import_indices = find_placement_imports(&asset, placement);
+                if import_indices.is_empty() {
+                    warn!("Failed to find placement {} in {}", placement, map_path);
+                }
+                removed_imports.extend(import_indices);
             }
 
-            let export_index = export_index.unwrap();
-            let mut name_map = asset.get_name_map();
-            let export = (asset.asset_data.exports[*export_index])
-                .get_normal_export_mut()
-                .unwrap();
-
-            let biome_property_name = match modifier.biome_type {
-                BiomeType::Surface => "SurfaceBiomes",
-                BiomeType::Crust => "CrustBiome",
-            };
-
-            let mut biome_property_index = None;
-            for i in 0..export.properties.len() {
-                let property = &export.properties[i];
-                if property
-                    .get_name()
-                    .get_content(|e| e == biome_property_name)
-                {
-                    biome_property_index = Some(i);
-                    break;
+            let mut replaced_imports = Vec::new();
+            for (placement, replacement_path) in &modifier.replace {
+                let import_indices = find_placement_imports(&asset, placement);
+                if import_indices.is_empty() {
+                    warn!("Failed to find placement {} in {}", placement, map_path);
+                    continue;
                 }
-            }
 
-            let biome_property_index = biome_property_index.ok_or_else(|| {
-                io::Error::new(
-                    ErrorKind::Other,
-                    format!("Failed to find biome type {biome_property_name}"),
-                )
-            })?;
-            let biome_property = cast!(
-                Property,
-                ArrayProperty,
-                &mut export.properties[biome_property_index]
-            )
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
-
-            let biome = biome_property
-                .value
-                .iter_mut()
-                .filter_map(|e| cast!(Property, StructProperty, e))
-                .find(|e| {
-                    e.value
-                        .iter()
-                        .filter_map(|e| cast!(Property, NameProperty, e))
-                        .any(|e| e.value.get_content(|e| e == modifier.biome_name))
-                })
-                .ok_or_else(|| {
-                    io::Error::new(
-                        ErrorKind::Other,
-                        format!("Failed to find biome {}", modifier.biome_name),
-                    )
-                })?;
+                let replacement = add_placement_import(&mut asset, replacement_path)?;
+                for import_index in import_indices {
+                    replaced_imports.push((import_index, replacement));
+                }
+            }
 
-            let layers = biome
-                .value
-                .iter_mut()
-                .find(|e| e.get_name().get_content(|e| e == "Layers"))
-                .and_then(|e| cast!(Property, ArrayProperty, e))
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
-
-            let layer = layers
-                .value
-                .iter_mut()
-                .filter_map(|e| cast!(Property, StructProperty, e))
-                .find(|e| {
-                    e.value
-                        .iter()
-                        .filter_map(|e| cast!(Property, NameProperty, e))
-                        .any(|e| e.value.get_content(|e| e == modifier.layer_name))
-                })
-                .ok_or_else(|| {
-                    io::Error::new(
-                        ErrorKind::Other,
-                        format!(
-                            "Failed to find layer {} for biome {}",
-                            modifier.layer_name, modifier.biome_name
-                        ),
-                    )
-                })?;
+            let biome_property_name = modifier.biome_type.property_name();
 
-            let object_placement_modifiers = layer
-                .value
-                .iter_mut()
-                .find(|e| {
-                    e.get_name()
-                        .get_content(|e| e == "ObjectPlacementModifiers")
-                })
-                .and_then(|e| cast!(Property, ArrayProperty, e))
-                .ok_or_else(|| {
-                    io::Error::new(ErrorKind::Other, "Corrupted game installation".to_string())
-                })?;
+            for (voxels_name, export_index) in voxel_names {
+                let mut name_map = asset.get_name_map();
+                let export = (asset.asset_data.exports[export_index])
+                    .get_normal_export_mut()
+                    .unwrap();
 
-            for import_index in &modifier_imports {
-                let placement_modifier = ObjectProperty {
-                    name: name_map
-                        .get_mut()
-                        .add_fname(&object_placement_modifiers.value.len().to_string()),
-                    ancestry: Ancestry::default(),
-                    property_guid: Some(Guid::default()),
-                    duplication_index: 0,
-                    value: *import_index,
+                let Some(biome_property) = export
+                    .properties
+                    .iter_mut()
+                    .find(|e| e.get_name().get_content(|e| e == biome_property_name))
+                    .and_then(|e| cast!(Property, ArrayProperty, e))
+                else {
+                    warn!(
+                        "Failed to find biome type {} for {} in {}",
+                        biome_property_name, voxels_name, map_path
+                    );
+                    continue;
                 };
-                object_placement_modifiers
+
+                let mut applied = false;
+                for biome in biome_property
                     .value
-                    .push(placement_modifier.into());
+                    .iter_mut()
+                    .filter_map(|e| cast!(Property, StructProperty, e))
+                    .filter(|e| has_matching_name(&e.value, &modifier.biome_name))
+                {
+                    let layers = biome
+                        .value
+                        .iter_mut()
+                        .find(|e| e.get_name().get_content(|e| e == "Layers"))
+                        .and_then(|e| cast!(Property, ArrayProperty, e))
+                        .ok_or_else(|| {
+                            io::Error::new(ErrorKind::Other, "Corrupted game installation")
+                        })?;
+
+                    for layer in layers
+                        .value
+                        .iter_mut()
+                        .filter_map(|e| cast!(Property, StructProperty, e))
+                        .filter(|e| has_matching_name(&e.value, &modifier.layer_name))
+                    {
+                        let object_placement_modifiers = layer
+                            .value
+                            .iter_mut()
+                            .find(|e| {
+                                e.get_name()
+                                    .get_content(|e| e == "ObjectPlacementModifiers")
+                            })
+                            .and_then(|e| cast!(Property, ArrayProperty, e))
+                            .ok_or_else(|| {
+                                io::Error::new(
+                                    ErrorKind::Other,
+                                    "Corrupted game installation".to_string(),
+                                )
+                            })?;
+
+                        object_placement_modifiers.value.retain(|e| {
+                            cast!(Property, ObjectProperty, e)
+                                .map(|e| !removed_imports.contains(&e.value))
+                                .unwrap_or(true)
+                        });
+
+                        for property in object_placement_modifiers
+                            .value
+                            .iter_mut()
+                            .filter_map(|e| cast!(Property, ObjectProperty, e))
+                        {
+                            if let Some((_, replacement)) = replaced_imports
+                                .iter()
+                                .find(|(import_index, _)| *import_index == property.value)
+                            {
+                                property.value = *replacement;
+                            }
+                        }
+
+                        for import_index in &modifier_imports {
+                            let placement_modifier = ObjectProperty {
+                                name: name_map
+                                    .get_mut()
+                                    .add_fname(&object_placement_modifiers.value.len().to_string()),
+                                ancestry: Ancestry::default(),
+                                property_guid: Some(Guid::default()),
+                                duplication_index: 0,
+                                value: *import_index,
+                            };
+                            object_placement_modifiers
+                                .value
+                                .push(placement_modifier.into());
+                        }
+                        applied = true;
+                    }
+                }
+
+                match applied {
+                    true => applied_maps.push(format!("{map_path} ({voxels_name})")),
+                    false => warn!(
+                        "Failed to find layer {} for biome {} of {} in {}",
+                        modifier.layer_name, modifier.biome_name, voxels_name, map_path
+                    ),
+                }
             }
         }
 
@@ -248,5 +256,138 @@ pub(crate) fn handle_biome_placement_modifiers(
             .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
     }
 
+    for (modifier, applied_maps) in biome_placement_modifiers.iter().zip(&applied_maps) {
+        match applied_maps.is_empty() {
+            true => warn!(
+                "Biome placement modifier for {} {} {} was not applied anywhere",
+                modifier.planet_type, modifier.biome_name, modifier.layer_name
+            ),
+            false => info!(
+                "Applied biome placement modifier for {} {} {} to {}",
+                modifier.planet_type,
+                modifier.biome_name,
+                modifier.layer_name,
+                applied_maps.join(", ")
+            ),
+        }
+    }
+
     Ok(())
 }
+
+/// Find planet voxel exports in a map, keyed by export name
+pub(crate) fn get_voxel_exports<C: Read + Seek>(
+    asset: &Asset<C>,
+) -> Result<BTreeMap<String, usize>, io::Error> {
+    let mut voxel_exports = BTreeMap::new();
+
+    for i in 0..asset.asset_data.exports.len() {
+        let export = &asset.asset_data.exports[i];
+        if let Some(normal_export) = export.get_normal_export() {
+            let class_index = normal_export.base_export.class_index;
+            if class_index.is_import() {
+                let import = asset.get_import(class_index).ok_or_else(|| {
+                    io::Error::new(ErrorKind::Other, "Corrupted game installation")
+                })?;
+
+                if import.object_name.get_owned_content() == "VoxelVolumeComponent"
+                    && normal_export.base_export.object_name.get_owned_content()
+                        != "Default Voxel Volume"
+                {
+                    voxel_exports
+                        .insert(normal_export.base_export.object_name.get_owned_content(), i);
+                }
+            }
+        }
+    }
+
+    Ok(voxel_exports)
+}
+
+/// Filter voxel exports of planets present in a map by a planet type, which may end with `*`
+pub(crate) fn filter_voxel_exports<'a>(
+    voxel_exports: &'a BTreeMap<String, usize>,
+    game_map: &'a GameMap,
+    planet_type: &'a str,
+) -> impl Iterator<Item = (&'a String, usize)> + 'a {
+    voxel_exports
+        .iter()
+        .filter(move |(name, _)| {
+            name.strip_suffix("Voxels")
+                .map(|planet| {
+                    matches_wildcard(planet_type, planet) && game_map.contains_planet(planet)
+                })
+                .unwrap_or(false)
+        })
+        .map(|(name, index)| (name, *index))
+}
+
+/// Check if the name property of a biome or layer struct matches a pattern
+pub(crate) fn has_matching_name(properties: &[Property], pattern: &str) -> bool {
+    properties
+        .iter()
+        .filter_map(|e| cast!(Property, NameProperty, e))
+        .any(|e| e.value.get_content(|e| matches_wildcard(pattern, e)))
+}
+
+/// Add imports for a procedural modifier
+fn add_placement_import<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    placement_path: &str,
+) -> Result<PackageIndex, io::Error> {
+    let placement_name = Path::new(placement_path)
+        .file_stem()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                format!("Invalid placement {placement_path}"),
+            )
+        })?;
+
+    let package_import = Import {
+        class_package: asset.add_fname("/Script/CoreUObject"),
+        class_name: asset.add_fname("Package"),
+        outer_index: PackageIndex::new(0),
+        object_name: asset.add_fname(placement_path),
+        optional: false,
+    };
+    let package_import = asset.add_import(package_import);
+
+    let modifier_import = Import {
+        class_package: asset.add_fname("/Script/Terrain2"),
+        class_name: asset.add_fname("ProceduralModifier"),
+        outer_index: package_import,
+        object_name: asset.add_fname(placement_name),
+        optional: false,
+    };
+    Ok(asset.add_import(modifier_import))
+}
+
+/// Find procedural modifier imports matching a name or path
+///
+/// Names match modifiers from any package, paths only the modifier in that package.
+fn find_placement_imports<C: Read + Seek>(asset: &Asset<C>, placement: &str) -> Vec<PackageIndex> {
+    let Some(placement_name) = Path::new(placement).file_stem().and_then(|e| e.to_str()) else {
+        return Vec::new();
+    };
+    let package = placement.contains('/').then_some(placement);
+
+    asset
+        .imports
+        .iter()
+        .enumerate()
+        .filter(|(_, import)| {
+            import.class_name.get_content(|e| e == "ProceduralModifier")
+                && import.object_name.get_content(|e| e == placement_name)
+                && match package {
+                    Some(package) => asset
+                        .get_import(import.outer_index)
+                        .map(|e| e.object_name.get_content(|e| e == package))
+                        .unwrap_or(false),
+                    None => true,
+                }
+        })
+        .map(|(i, _)| PackageIndex::new(-(i as i32) - 1))
+        .collect()
+}
@@ -0,0 +1,221 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::{base_export::BaseExport, Export, ExportBaseTrait},
+    types::PackageIndex,
+    Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::linked_actor_components::add_class_imports;
+use super::properties::find_cdo;
+use crate::AstroIntegratorConfig;
+
+/// Maximum number of blueprints walked when checking the class hierarchy
+const MAX_HIERARCHY_DEPTH: usize = 32;
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_blueprint_parents(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    blueprint_parents_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut new_parents: HashMap<String, String> = HashMap::new();
+
+    for blueprint_parents_map in blueprint_parents_maps {
+        let blueprint_parents_map: HashMap<String, String> =
+            serde_json::from_value(blueprint_parents_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (name, parent) in blueprint_parents_map {
+            match new_parents.get(&name) {
+                Some(existing) if existing != &parent => {
+                    return Err(io::Error::new(
+                        ErrorKind::Other,
+                        format!("Conflicting parent classes {existing} and {parent} for {name}"),
+                    )
+                    .into());
+                }
+                _ => {
+                    new_parents.insert(name, parent);
+                }
+            }
+        }
+    }
+
+    for (name, parent) in &new_parents {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let class_index = asset
+            .asset_data
+            .exports
+            .iter()
+            .position(|e| cast!(Export, ClassExport, e).is_some())
+            .ok_or_else(|| {
+                io::Error::new(ErrorKind::Other, format!("Failed to find class in {name}"))
+            })?;
+        let cdo_index = find_cdo(&asset, name)?;
+
+        let old_super = cast!(Export, ClassExport, &asset.asset_data.exports[class_index])
+            .expect("Corrupted memory")
+            .struct_export
+            .super_struct;
+        let (old_super_package, old_super_name) = asset
+            .get_import(old_super)
+            .and_then(|e| {
+                let package = asset.get_import(e.outer_index)?;
+                Some((
+                    package.object_name.get_owned_content(),
+                    e.object_name.get_owned_content(),
+                ))
+            })
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Import not found"))?;
+        let old_template = asset.asset_data.exports[cdo_index]
+            .get_base_export()
+            .template_index;
+
+        if !derives_from(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            name,
+            parent,
+            (&old_super_package, &old_super_name),
+        )? {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("{parent} does not derive from {old_super_name}, the parent of {name}"),
+            )
+            .into());
+        }
+
+        let (new_super, new_template) = add_class_imports(&mut asset, parent)?;
+
+        let class_export = cast!(
+            Export,
+            ClassExport,
+            &mut asset.asset_data.exports[class_index]
+        )
+        .expect("Corrupted memory");
+        class_export.struct_export.super_struct = new_super;
+        replace_dependency(
+            &mut class_export.struct_export.normal_export.base_export,
+            old_super,
+            new_super,
+        );
+
+        let cdo_export = asset.asset_data.exports[cdo_index].get_base_export_mut();
+        cdo_export.template_index = new_template;
+        replace_dependency(cdo_export, old_template, new_template);
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Check if a blueprint derives from a class, directly or through other blueprints
+///
+/// Classes are compared by package and class name. Fails if the hierarchy of
+/// `blueprint` contains `reparented`, the blueprint it would become the parent
+/// of, as that would make the class hierarchy circular.
+fn derives_from(
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    reparented: &str,
+    blueprint: &str,
+    (class_package, class_name): (&str, &str),
+) -> Result<bool, io::Error> {
+    let mut blueprint = String::from(blueprint);
+
+    for _ in 0..MAX_HIERARCHY_DEPTH {
+        if blueprint == reparented {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("{reparented} can't derive from itself"),
+            ));
+        }
+
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, &blueprint)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let Some((parent_name, parent_package)) = get_parent_class(&asset) else {
+            return Ok(false);
+        };
+        if parent_package == class_package && parent_name == class_name {
+            return Ok(true);
+        }
+
+        // native classes end the hierarchy, blueprints are followed to their own parent
+        blueprint = parent_package;
+        if !blueprint.starts_with("/Game/") {
+            return Ok(false);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Get the name and package of a blueprint's parent class
+fn get_parent_class<C: Read + Seek>(asset: &Asset<C>) -> Option<(String, String)> {
+    let class_export = asset
+        .asset_data
+        .exports
+        .iter()
+        .find_map(|e| cast!(Export, ClassExport, e))?;
+    let parent = asset.get_import(class_export.struct_export.super_struct)?;
+    let package = asset.get_import(parent.outer_index)?;
+
+    Some((
+        parent.object_name.get_owned_content(),
+        package.object_name.get_owned_content(),
+    ))
+}
+
+/// Replace an export dependency with another one
+fn replace_dependency(
+    base_export: &mut BaseExport<PackageIndex>,
+    old: PackageIndex,
+    new: PackageIndex,
+) {
+    for dependencies in [
+        &mut base_export.serialization_before_serialization_dependencies,
+        &mut base_export.create_before_serialization_dependencies,
+        &mut base_export.serialization_before_create_dependencies,
+        &mut base_export.create_before_create_dependencies,
+    ] {
+        for dependency in dependencies.iter_mut().filter(|e| **e == old) {
+            *dependency = new;
+        }
+    }
+}
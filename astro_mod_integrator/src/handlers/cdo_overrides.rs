@@ -0,0 +1,180 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{engine_version::EngineVersion, Asset};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::properties::{find_cdo, set_export_property, PropertyType};
+use crate::AstroIntegratorConfig;
+
+/// A property value to set on a class default object
+///
+/// Properties still at their parent's default value are not serialized in
+/// the CDO, `property_type` allows adding them. Types can only be given for
+/// top level properties, nested fields are added through the type of the
+/// struct containing them.
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct PropertyOverride {
+    pub property: String,
+    pub value: serde_json::Value,
+    #[serde(default)]
+    pub property_type: Option<PropertyType>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_cdo_overrides(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    cdo_overrides_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut cdo_overrides: HashMap<String, Vec<PropertyOverride>> = HashMap::new();
+
+    for cdo_overrides_map in cdo_overrides_maps {
+        let cdo_overrides_map: HashMap<String, Vec<PropertyOverride>> =
+            serde_json::from_value(cdo_overrides_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (name, overrides) in cdo_overrides_map {
+            cdo_overrides.entry(name).or_default().extend(overrides);
+        }
+    }
+
+    for (name, overrides) in &cdo_overrides {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let cdo_index = find_cdo(&asset, name)?;
+
+        for property_override in overrides {
+            apply_property_override(&mut asset, cdo_index, property_override)
+                .map_err(|e| io::Error::new(ErrorKind::Other, format!("{name}: {e}")))?;
+        }
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Set an overridden property on an export
+pub(crate) fn apply_property_override<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    export_index: usize,
+    property_override: &PropertyOverride,
+) -> Result<(), io::Error> {
+    if property_override.property_type.is_some() && property_override.property.contains(['.', '['])
+    {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            format!(
+                "property_type can't be given for nested property {}",
+                property_override.property
+            ),
+        ));
+    }
+
+    set_export_property(
+        asset,
+        export_index,
+        &property_override.property,
+        &property_override.value,
+        property_override.property_type.as_ref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use unreal_mod_manager::unreal_asset::{
+        cast,
+        exports::ExportNormalTrait,
+        properties::{Property, PropertyDataTrait},
+    };
+
+    use super::*;
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+
+    fn test_asset() -> (Asset<Cursor<Vec<u8>>>, usize) {
+        let asset = Asset::new(
+            Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+            Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+            EngineVersion::VER_UE4_27,
+            None,
+        )
+        .expect("Corrupted ActorTemplate");
+        let export_index = asset
+            .asset_data
+            .exports
+            .iter()
+            .position(|e| e.get_normal_export().is_some())
+            .expect("Missing normal export");
+        (asset, export_index)
+    }
+
+    fn property_override(value: serde_json::Value) -> PropertyOverride {
+        serde_json::from_value(value).expect("Invalid override")
+    }
+
+    #[test]
+    fn override_unserialized_property() {
+        let (mut asset, export_index) = test_asset();
+
+        let without_type = property_override(serde_json::json!({
+            "property": "TestValue",
+            "value": 5
+        }));
+        assert!(apply_property_override(&mut asset, export_index, &without_type).is_err());
+
+        let with_type = property_override(serde_json::json!({
+            "property": "TestValue",
+            "value": 5,
+            "property_type": "IntProperty"
+        }));
+        apply_property_override(&mut asset, export_index, &with_type).unwrap();
+        apply_property_override(&mut asset, export_index, &without_type).unwrap();
+
+        let properties = &asset.asset_data.exports[export_index]
+            .get_normal_export()
+            .unwrap()
+            .properties;
+        let values = properties
+            .iter()
+            .filter(|e| e.get_name().get_content(|e| e == "TestValue"))
+            .map(|e| cast!(Property, IntProperty, e).expect("Not an int").value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [5]);
+    }
+
+    #[test]
+    fn nested_property_type_is_rejected() {
+        let (mut asset, export_index) = test_asset();
+
+        let nested = property_override(serde_json::json!({
+            "property": "TestStruct.Value",
+            "value": 5,
+            "property_type": "IntProperty"
+        }));
+        assert!(apply_property_override(&mut asset, export_index, &nested).is_err());
+    }
+}
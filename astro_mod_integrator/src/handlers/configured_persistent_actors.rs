@@ -0,0 +1,213 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    engine_version::EngineVersion, exports::ExportNormalTrait, properties::PropertyDataTrait, Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::cdo_overrides::{apply_property_override, PropertyOverride};
+use super::properties::{copy_property, find_cdo};
+use super::MAP_PATHS;
+use crate::AstroIntegratorConfig;
+
+/// Settings for a persistent actor
+///
+/// The actor itself is still injected through `persistent_actors`, these
+/// settings are applied to the actors of its class placed in the persistent
+/// actor maps. Without `maps` the settings apply in every persistent actor
+/// map. Properties without a `property_type` that the placed actor doesn't
+/// serialize are copied from the actor's class default object.
+#[derive(Deserialize, Serialize, Debug)]
+struct PersistentActorConfig {
+    #[serde(default)]
+    pub maps: Option<Vec<String>>,
+    #[serde(default)]
+    pub properties: Vec<PropertyOverride>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_configured_persistent_actors(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    persistent_actors_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut actors: Vec<(String, PersistentActorConfig)> = Vec::new();
+
+    for persistent_actors_map in persistent_actors_maps {
+        let persistent_actors_map: BTreeMap<String, PersistentActorConfig> =
+            serde_json::from_value(persistent_actors_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+        actors.extend(persistent_actors_map);
+    }
+
+    let mut map_actors: BTreeMap<&str, Vec<&(String, PersistentActorConfig)>> = BTreeMap::new();
+
+    for entry in &actors {
+        let (actor, config) = entry;
+        let map_paths = match &config.maps {
+            Some(maps) => maps
+                .iter()
+                .map(|map_name| {
+                    let map_path = game_to_absolute(AstroIntegratorConfig::GAME_NAME, map_name)
+                        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid map name"))?;
+                    MAP_PATHS
+                        .iter()
+                        .find(|e| **e == map_path)
+                        .copied()
+                        .ok_or_else(|| {
+                            io::Error::new(
+                                ErrorKind::Other,
+                                format!("Unsupported map {map_name} for persistent actor {actor}"),
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::from(MAP_PATHS),
+        };
+
+        for map_path in map_paths {
+            map_actors.entry(map_path).or_default().push(entry);
+        }
+    }
+
+    for (map_path, entries) in &map_actors {
+        let map_path = map_path.to_string();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &map_path,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for (actor, config) in entries {
+            let actor_indices = find_placed_actors(&asset, actor);
+            if actor_indices.is_empty() {
+                warn!(
+                    "Persistent actor {} is not placed in {}, list it in persistent_actors",
+                    actor, map_path
+                );
+                continue;
+            }
+
+            let actor_path = game_to_absolute(AstroIntegratorConfig::GAME_NAME, actor)
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+            let actor_asset = get_asset(
+                integrated_pak,
+                game_paks,
+                mod_paks,
+                &actor_path,
+                EngineVersion::VER_UE4_27,
+            )?;
+            let cdo_index = find_cdo(&actor_asset, actor)?;
+
+            for actor_index in actor_indices {
+                for property_override in &config.properties {
+                    if property_override.property_type.is_none() {
+                        add_cdo_property(
+                            &mut asset,
+                            actor_index,
+                            &property_override.property,
+                            &actor_asset,
+                            cdo_index,
+                        )?;
+                    }
+                    apply_property_override(&mut asset, actor_index, property_override)
+                        .map_err(|e| io::Error::new(ErrorKind::Other, format!("{actor}: {e}")))?;
+                }
+            }
+        }
+
+        write_asset(integrated_pak, &asset, &map_path)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Find the exports of the actors of a blueprint class placed in a map
+fn find_placed_actors<C: Read + Seek>(asset: &Asset<C>, actor: &str) -> Vec<usize> {
+    let package_name = actor.split('.').next().unwrap_or(actor);
+    let class_name = format!("{}_C", package_name.rsplit('/').next().unwrap_or(actor));
+
+    asset
+        .asset_data
+        .exports
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| {
+            e.get_normal_export()
+                .filter(|e| e.base_export.class_index.is_import())
+                .and_then(|e| asset.get_import(e.base_export.class_index))
+                .and_then(|import| {
+                    let package = asset.get_import(import.outer_index)?;
+                    Some(
+                        import.object_name.get_content(|e| e == class_name)
+                            && package.object_name.get_content(|e| e == package_name),
+                    )
+                })
+                .unwrap_or(false)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Copy a property the placed actor doesn't serialize from the class default object
+fn add_cdo_property<C: Read + Seek, D: Read + Seek>(
+    asset: &mut Asset<C>,
+    actor_index: usize,
+    property_path: &str,
+    actor_asset: &Asset<D>,
+    cdo_index: usize,
+) -> Result<(), io::Error> {
+    let name = match property_path.find(['.', '[']) {
+        Some(index) => &property_path[..index],
+        None => property_path,
+    };
+
+    let exists = asset.asset_data.exports[actor_index]
+        .get_normal_export()
+        .map(|e| {
+            e.properties
+                .iter()
+                .any(|e| e.get_name().get_content(|e| e == name))
+        })
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+
+    let Some(cdo_property) = actor_asset.asset_data.exports[cdo_index]
+        .get_normal_export()
+        .and_then(|e| {
+            e.properties
+                .iter()
+                .find(|e| e.get_name().get_content(|e| e == name))
+        })
+    else {
+        return Ok(());
+    };
+
+    let property = copy_property(asset, actor_asset, cdo_property)?;
+    asset.asset_data.exports[actor_index]
+        .get_normal_export_mut()
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Actor is not a normal export"))?
+        .properties
+        .push(property);
+
+    Ok(())
+}
@@ -0,0 +1,234 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::ExportNormalTrait,
+    properties::{Property, PropertyDataTrait},
+    Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::properties::{
+    array_element_template, find_cdo, find_property, find_property_mut, parse_property_path,
+    property_from_json, set_export_property, PropertyType,
+};
+use crate::AstroIntegratorConfig;
+
+/// Path of the ingredient array on item type CDOs
+const INGREDIENTS_PATH: &str = "CatalogData.Ingredients";
+const INGREDIENT_STRUCT: &str = "ItemRecipeIngredient";
+const INGREDIENT_ITEM_TYPE: &str = "ItemType";
+const INGREDIENT_COUNT: &str = "Count";
+
+/// Type of the ingredient array, used when the item has no ingredients yet
+fn ingredients_type() -> PropertyType {
+    PropertyType::Array {
+        array: Box::new(PropertyType::Struct {
+            struct_type: Some(String::from(INGREDIENT_STRUCT)),
+            fields: BTreeMap::from([
+                (
+                    String::from(INGREDIENT_ITEM_TYPE),
+                    PropertyType::Simple(String::from("ObjectProperty")),
+                ),
+                (
+                    String::from(INGREDIENT_COUNT),
+                    PropertyType::Simple(String::from("IntProperty")),
+                ),
+            ]),
+        }),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum RecipeOperation {
+    /// Replace all ingredients
+    #[default]
+    Set,
+    /// Change the count of the given ingredients, adding missing ones and
+    /// removing the ones with a count of zero
+    Edit,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Ingredient {
+    pub item_type: String,
+    pub count: u32,
+}
+
+/// A change to the ingredients a printer consumes for an item type
+#[derive(Deserialize, Serialize, Debug)]
+struct RecipeEdit {
+    pub item_type: String,
+    #[serde(default)]
+    pub operation: RecipeOperation,
+    pub ingredients: Vec<Ingredient>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_crafting_recipes(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    crafting_recipes: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut recipe_edits: HashMap<String, Vec<RecipeEdit>> = HashMap::new();
+
+    for mod_recipes in crafting_recipes {
+        let mod_recipes: Vec<RecipeEdit> = serde_json::from_value(mod_recipes.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for recipe in mod_recipes {
+            recipe_edits
+                .entry(recipe.item_type.clone())
+                .or_default()
+                .push(recipe);
+        }
+    }
+
+    let segments = parse_property_path(INGREDIENTS_PATH)?;
+
+    for (item_type, edits) in &recipe_edits {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, item_type)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let cdo_index = find_cdo(&asset, item_type)?;
+
+        let ingredients_type = ingredients_type();
+        let ingredients_exist = asset.asset_data.exports[cdo_index]
+            .get_normal_export()
+            .and_then(|e| find_property(&e.properties, &segments))
+            .is_some();
+        if !ingredients_exist {
+            set_export_property(
+                &mut asset,
+                cdo_index,
+                INGREDIENTS_PATH,
+                &serde_json::Value::Array(Vec::new()),
+                Some(&ingredients_type),
+            )
+            .map_err(|e| io::Error::new(ErrorKind::Other, format!("{item_type}: {e}")))?;
+        }
+
+        let ingredients_not_found = || {
+            io::Error::new(
+                ErrorKind::Other,
+                format!("Failed to find {INGREDIENTS_PATH} in {item_type}"),
+            )
+        };
+        let ingredients_array = asset.asset_data.exports[cdo_index]
+            .get_normal_export()
+            .and_then(|e| find_property(&e.properties, &segments))
+            .and_then(|e| cast!(Property, ArrayProperty, e))
+            .ok_or_else(ingredients_not_found)?
+            .clone();
+        let template =
+            array_element_template(&mut asset, &ingredients_array, ingredients_type.element())?;
+        let mut ingredients = ingredients_array.value;
+
+        for edit in edits {
+            if edit.operation == RecipeOperation::Set {
+                ingredients.clear();
+            }
+
+            for ingredient in &edit.ingredients {
+                let existing = ingredients
+                    .iter()
+                    .position(|e| ingredient_matches(&asset, e, &ingredient.item_type));
+
+                match (existing, ingredient.count) {
+                    (Some(index), 0) => {
+                        ingredients.remove(index);
+                    }
+                    (None, 0) => {
+                        warn!(
+                            "Ingredient {} to remove not found in {}",
+                            ingredient.item_type, item_type
+                        );
+                    }
+                    (existing, count) => {
+                        let value = serde_json::json!({
+                            INGREDIENT_ITEM_TYPE: ingredient.item_type,
+                            INGREDIENT_COUNT: count,
+                        });
+                        let property = property_from_json(&mut asset, &template, &value)?;
+                        match existing {
+                            Some(index) => ingredients[index] = property,
+                            None => ingredients.push(property),
+                        }
+                    }
+                }
+            }
+        }
+
+        cast!(
+            Property,
+            ArrayProperty,
+            asset.asset_data.exports[cdo_index]
+                .get_normal_export_mut()
+                .and_then(|e| find_property_mut(&mut e.properties, &segments))
+                .ok_or_else(ingredients_not_found)?
+        )
+        .ok_or_else(ingredients_not_found)?
+        .value = ingredients;
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Check if an ingredient struct references the given item type
+fn ingredient_matches<C: Read + Seek>(
+    asset: &Asset<C>,
+    ingredient: &Property,
+    item_type: &str,
+) -> bool {
+    let Some(class_name) = Path::new(item_type)
+        .file_stem()
+        .and_then(|e| e.to_str())
+        .map(|e| String::from(e) + "_C")
+    else {
+        return false;
+    };
+
+    cast!(Property, StructProperty, ingredient)
+        .and_then(|e| {
+            e.value
+                .iter()
+                .find(|e| e.get_name().get_content(|e| e == INGREDIENT_ITEM_TYPE))
+        })
+        .and_then(|e| cast!(Property, ObjectProperty, e))
+        .and_then(|e| asset.get_import(e.value))
+        .and_then(|import| {
+            let package = asset.get_import(import.outer_index)?;
+            Some(
+                import.object_name.get_content(|e| e == class_name)
+                    && package.object_name.get_content(|e| e == item_type),
+            )
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,385 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::Export,
+    properties::{struct_property::StructProperty, Property},
+    Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::properties::{typed_property_from_json, PropertyType};
+use crate::AstroIntegratorConfig;
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum RowOperation {
+    /// Add a new row
+    #[default]
+    Add,
+    /// Replace an existing row with a new one
+    Replace,
+    /// Remove an existing row
+    Remove,
+}
+
+/// A change to a single DataTable row
+///
+/// New rows are based on the required `template` row, replaced rows on the
+/// existing row or on `template` if given, so `values` only need to contain
+/// the fields that differ from it. Fields still at their default value are
+/// not serialized in the base row, they are only added if their type is given
+/// in `value_types`.
+#[derive(Deserialize, Serialize, Debug)]
+struct RowEdit {
+    pub row: String,
+    #[serde(default)]
+    pub operation: RowOperation,
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub values: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub value_types: BTreeMap<String, PropertyType>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_data_table_rows(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    data_table_rows_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut table_edits: HashMap<String, Vec<RowEdit>> = HashMap::new();
+
+    for data_table_rows_map in data_table_rows_maps {
+        let data_table_rows_map: HashMap<String, Vec<RowEdit>> =
+            serde_json::from_value(data_table_rows_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (table_name, edits) in data_table_rows_map {
+            table_edits.entry(table_name).or_default().extend(edits);
+        }
+    }
+
+    for (table_name, edits) in &table_edits {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, table_name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let table_index = asset
+            .asset_data
+            .exports
+            .iter()
+            .position(|e| cast!(Export, DataTableExport, e).is_some())
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!("Failed to find DataTable in {table_name}"),
+                )
+            })?;
+
+        let table = &mut cast!(
+            Export,
+            DataTableExport,
+            &mut asset.asset_data.exports[table_index]
+        )
+        .expect("Corrupted memory")
+        .table;
+        let mut rows = std::mem::take(&mut table.data);
+
+        for edit in edits {
+            apply_row_edit(&mut asset, &mut rows, table_name, edit)?;
+        }
+
+        cast!(
+            Export,
+            DataTableExport,
+            &mut asset.asset_data.exports[table_index]
+        )
+        .expect("Corrupted memory")
+        .table
+        .data = rows;
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Apply an edit to the rows of a DataTable
+fn apply_row_edit<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    rows: &mut Vec<StructProperty>,
+    table_name: &str,
+    edit: &RowEdit,
+) -> Result<(), io::Error> {
+    let existing_row = rows
+        .iter()
+        .position(|e| e.name.get_content(|e| e == edit.row));
+    let find_template = |template: &str| {
+        rows.iter()
+            .find(|e| e.name.get_content(|e| e == template))
+            .cloned()
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "Failed to find template row {template} for {} in {table_name}",
+                        edit.row
+                    ),
+                )
+            })
+    };
+
+    let base = match (edit.operation, existing_row) {
+        (RowOperation::Remove, Some(row_index)) => {
+            rows.remove(row_index);
+            return Ok(());
+        }
+        (RowOperation::Add, Some(_)) => {
+            warn!("Row {} already exists in {}", edit.row, table_name);
+            return Ok(());
+        }
+        (RowOperation::Replace, Some(row_index)) => match &edit.template {
+            Some(template) => find_template(template)?,
+            None => rows[row_index].clone(),
+        },
+        (RowOperation::Add, None) => {
+            let template = edit.template.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!("Missing template for new row {} in {table_name}", edit.row),
+                )
+            })?;
+            find_template(template)?
+        }
+        (_, None) => {
+            warn!("Failed to find row {} in {}", edit.row, table_name);
+            return Ok(());
+        }
+    };
+
+    let row_type = PropertyType::Struct {
+        struct_type: None,
+        fields: edit.value_types.clone(),
+    };
+    let mut row = match typed_property_from_json(
+        asset,
+        &base.into(),
+        &serde_json::Value::Object(edit.values.clone()),
+        Some(&row_type),
+    )? {
+        Property::StructProperty(row) => row,
+        _ => {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("Row {} in {table_name} is not a struct", edit.row),
+            ))
+        }
+    };
+    row.name = asset.add_fname(&edit.row);
+
+    match existing_row {
+        Some(row_index) => rows[row_index] = row,
+        None => rows.push(row),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use unreal_mod_manager::unreal_asset::{
+        properties::{int_property::IntProperty, PropertyDataTrait},
+        unversioned::ancestry::Ancestry,
+    };
+
+    use super::*;
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+
+    fn test_asset() -> Asset<Cursor<Vec<u8>>> {
+        Asset::new(
+            Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+            Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+            EngineVersion::VER_UE4_27,
+            None,
+        )
+        .expect("Corrupted ActorTemplate")
+    }
+
+    fn row<C: Read + Seek>(
+        asset: &mut Asset<C>,
+        name: &str,
+        fields: &[(&str, i32)],
+    ) -> StructProperty {
+        StructProperty {
+            name: asset.add_fname(name),
+            ancestry: Ancestry::default(),
+            struct_type: Some(asset.add_fname("TestRow")),
+            struct_guid: None,
+            property_guid: None,
+            duplication_index: 0,
+            serialize_none: true,
+            value: fields
+                .iter()
+                .map(|(field, value)| {
+                    IntProperty {
+                        name: asset.add_fname(field),
+                        ancestry: Ancestry::default(),
+                        property_guid: None,
+                        duplication_index: 0,
+                        value: *value,
+                    }
+                    .into()
+                })
+                .collect(),
+        }
+    }
+
+    fn field(rows: &[StructProperty], row: &str, field: &str) -> i32 {
+        let row = rows
+            .iter()
+            .find(|e| e.name.get_content(|e| e == row))
+            .expect("Missing row");
+        row.value
+            .iter()
+            .find(|e| e.get_name().get_content(|e| e == field))
+            .and_then(|e| cast!(Property, IntProperty, e))
+            .expect("Missing field")
+            .value
+    }
+
+    fn edit(value: serde_json::Value) -> RowEdit {
+        serde_json::from_value(value).expect("Invalid edit")
+    }
+
+    #[test]
+    fn add_row_from_template() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([row(&mut asset, "A", &[("X", 1), ("Y", 2)])]);
+
+        let add = edit(serde_json::json!({ "row": "B", "template": "A", "values": { "X": 5 } }));
+        apply_row_edit(&mut asset, &mut rows, "Table", &add).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(field(&rows, "B", "X"), 5);
+        assert_eq!(field(&rows, "B", "Y"), 2);
+        assert_eq!(field(&rows, "A", "X"), 1);
+    }
+
+    #[test]
+    fn add_row_requires_template() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([row(&mut asset, "A", &[("X", 1)])]);
+
+        let add = edit(serde_json::json!({ "row": "B", "values": { "X": 5 } }));
+        assert!(apply_row_edit(&mut asset, &mut rows, "Table", &add).is_err());
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn add_existing_row_is_skipped() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([row(&mut asset, "A", &[("X", 1)])]);
+
+        let add = edit(serde_json::json!({ "row": "A", "template": "A", "values": { "X": 5 } }));
+        apply_row_edit(&mut asset, &mut rows, "Table", &add).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(field(&rows, "A", "X"), 1);
+    }
+
+    #[test]
+    fn replace_row_keeps_its_own_fields() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([
+            row(&mut asset, "A", &[("X", 1), ("Y", 2)]),
+            row(&mut asset, "B", &[("X", 3), ("Y", 4)]),
+        ]);
+
+        let replace = edit(serde_json::json!({
+            "row": "B",
+            "operation": "replace",
+            "values": { "X": 7 }
+        }));
+        apply_row_edit(&mut asset, &mut rows, "Table", &replace).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].name.get_content(|e| e == "B"));
+        assert_eq!(field(&rows, "B", "X"), 7);
+        assert_eq!(field(&rows, "B", "Y"), 4);
+    }
+
+    #[test]
+    fn replace_row_from_template() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([
+            row(&mut asset, "A", &[("X", 1), ("Y", 2)]),
+            row(&mut asset, "B", &[("X", 3), ("Y", 4)]),
+        ]);
+
+        let replace = edit(serde_json::json!({
+            "row": "B",
+            "operation": "replace",
+            "template": "A",
+            "values": { "X": 7 }
+        }));
+        apply_row_edit(&mut asset, &mut rows, "Table", &replace).unwrap();
+
+        assert_eq!(field(&rows, "B", "X"), 7);
+        assert_eq!(field(&rows, "B", "Y"), 2);
+    }
+
+    #[test]
+    fn remove_row() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([
+            row(&mut asset, "A", &[("X", 1)]),
+            row(&mut asset, "B", &[("X", 3)]),
+        ]);
+
+        let remove = edit(serde_json::json!({ "row": "A", "operation": "remove" }));
+        apply_row_edit(&mut asset, &mut rows, "Table", &remove).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].name.get_content(|e| e == "B"));
+    }
+
+    #[test]
+    fn missing_row_is_skipped() {
+        let mut asset = test_asset();
+        let mut rows = Vec::from([row(&mut asset, "A", &[("X", 1)])]);
+
+        for operation in ["replace", "remove"] {
+            let edit = edit(serde_json::json!({ "row": "B", "operation": operation }));
+            apply_row_edit(&mut asset, &mut rows, "Table", &edit).unwrap();
+        }
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(field(&rows, "A", "X"), 1);
+    }
+}
@@ -0,0 +1,52 @@
+// also used by build.rs to validate baked mod metadata, so this file must not
+// depend on anything else in the crate
+
+use serde::{Deserialize, Serialize};
+
+/// Game mode a map belongs to
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GameMode {
+    BaseGame,
+    Expansion,
+}
+
+/// A game map along with the planets it contains
+#[derive(Debug)]
+pub(crate) struct GameMap {
+    pub path: &'static str,
+    pub game_mode: GameMode,
+    /// Planets with a voxel export in this map
+    pub planets: &'static [&'static str],
+}
+
+const BASE_GAME_PLANETS: [&str; 7] = [
+    "Terran",
+    "TerranMoon",
+    "Arid",
+    "Tundra",
+    "TundraMoon",
+    "Radiated",
+    "Exotic",
+];
+
+const EXPANSION_PLANETS: [&str; 1] = ["Lethe"];
+
+pub(crate) const GAME_MAPS: [GameMap; 3] = [
+    GameMap {
+        path: "Astro/Content/Maps/Staging_T2.umap",
+        game_mode: GameMode::BaseGame,
+        planets: &BASE_GAME_PLANETS,
+    },
+    GameMap {
+        path: "Astro/Content/Maps/Staging_T2_PackedPlanets_Switch.umap",
+        game_mode: GameMode::BaseGame,
+        planets: &BASE_GAME_PLANETS,
+    },
+    GameMap {
+        path: "Astro/Content/U32_Expansion/U32_Expansion.umap",
+        game_mode: GameMode::Expansion,
+        planets: &EXPANSION_PLANETS,
+    },
+];
+
+pub(crate) static MAP_PATHS: [&str; 3] = [GAME_MAPS[0].path, GAME_MAPS[1].path, GAME_MAPS[2].path];
@@ -119,7 +119,21 @@ pub(crate) fn handle_item_list_entries(
         }
     }
 
-    for (asset_name, entries) in &new_items {
+    add_item_list_entries(integrated_pak, game_paks, mod_paks, &new_items)
+}
+
+/// Append item types to the arrays of item lists
+///
+/// `new_items` maps item list assets to their arrays, e.g. `ItemTypes`, and
+/// the item types to append to them.
+#[allow(clippy::assigning_clones)]
+pub(crate) fn add_item_list_entries(
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    new_items: &HashMap<String, HashMap<String, Vec<String>>>,
+) -> Result<(), Error> {
+    for (asset_name, entries) in new_items {
         let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, asset_name)
             .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
         let mut asset = get_asset(
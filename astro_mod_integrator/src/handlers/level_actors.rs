@@ -0,0 +1,447 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, ErrorKind, Read, Seek};
+
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::types::PackageIndexTrait;
+use unreal_mod_manager::unreal_asset::unversioned::ancestry::Ancestry;
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::{normal_export::NormalExport, Export, ExportBaseTrait, ExportNormalTrait},
+    flags::EObjectFlags,
+    properties::{
+        array_property::ArrayProperty,
+        enum_property::EnumProperty,
+        object_property::ObjectProperty,
+        str_property::NameProperty,
+        struct_property::StructProperty,
+        vector_property::{RotatorProperty, VectorProperty},
+        Property, PropertyDataTrait,
+    },
+    types::{vector::Vector, PackageIndex},
+    Asset, Import,
+};
+use unreal_mod_manager::unreal_helpers::{game_to_absolute, Guid};
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::linked_actor_components::{add_class_imports, find_or_add_import};
+use super::MAP_PATHS;
+use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+use crate::AstroIntegratorConfig;
+
+/// A blueprint actor to place into a level
+///
+/// `rotation` is given as pitch, yaw and roll in degrees.
+#[derive(Deserialize, Serialize, Debug)]
+struct LevelActor {
+    pub actor: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub location: [f64; 3],
+    #[serde(default)]
+    pub rotation: [f64; 3],
+    #[serde(default = "default_scale")]
+    pub scale: [f64; 3],
+}
+
+fn default_scale() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// Root component of a blueprint, as created by its construction script
+///
+/// `class_type_package` and `class_type_name` are the class of the component
+/// class itself, e.g. `Class` for native components and
+/// `BlueprintGeneratedClass` for blueprint components.
+struct RootComponent {
+    name: String,
+    class_package: String,
+    class_name: String,
+    class_type_package: String,
+    class_type_name: String,
+    template_name: String,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_level_actors(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    level_actors_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let actor_asset = Asset::new(
+        Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+        Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+        EngineVersion::VER_UE4_27,
+        None,
+    )
+    .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+
+    let gen_variable = cast!(Export, NormalExport, &actor_asset.asset_data.exports[0])
+        .expect("Corrupted ActorTemplate");
+
+    let mut level_actors: HashMap<String, Vec<LevelActor>> = HashMap::new();
+
+    for level_actors_map in level_actors_maps {
+        let level_actors_map: HashMap<String, Vec<LevelActor>> =
+            serde_json::from_value(level_actors_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (map_name, actors) in level_actors_map {
+            let map_path = game_to_absolute(AstroIntegratorConfig::GAME_NAME, &map_name)
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid map name"))?;
+
+            if !MAP_PATHS.contains(&map_path.as_str()) {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Unsupported map {map_name} for level_actors"),
+                )
+                .into());
+            }
+
+            level_actors.entry(map_path).or_default().extend(actors);
+        }
+    }
+
+    let mut root_components: HashMap<String, RootComponent> = HashMap::new();
+
+    for (map_path, actors) in &level_actors {
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            map_path,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for actor in actors {
+            if !root_components.contains_key(&actor.actor) {
+                let root_component =
+                    load_root_component(integrated_pak, game_paks, mod_paks, &actor.actor)?;
+                root_components.insert(actor.actor.clone(), root_component);
+            }
+
+            add_level_actor(
+                &mut asset,
+                gen_variable,
+                actor,
+                &root_components[&actor.actor],
+            )?;
+        }
+
+        write_asset(integrated_pak, &asset, map_path)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Load a blueprint and get the root component its construction script creates
+fn load_root_component(
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    actor: &str,
+) -> Result<RootComponent, io::Error> {
+    let actor_path = game_to_absolute(AstroIntegratorConfig::GAME_NAME, actor)
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+    let actor_asset = get_asset(
+        integrated_pak,
+        game_paks,
+        mod_paks,
+        &actor_path,
+        EngineVersion::VER_UE4_27,
+    )?;
+
+    get_root_component(&actor_asset).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::Other,
+            format!("Failed to find root component of {actor}"),
+        )
+    })
+}
+
+/// Get the root component a blueprint's construction script creates
+fn get_root_component<C: Read + Seek>(asset: &Asset<C>) -> Option<RootComponent> {
+    let exports = &asset.asset_data.exports;
+
+    let scs_export = exports
+        .iter()
+        .filter_map(|e| e.get_normal_export())
+        .find(|e| {
+            e.base_export.class_index.is_import()
+                && asset
+                    .get_import(e.base_export.class_index)
+                    .map(|e| {
+                        e.object_name
+                            .get_content(|e| e == "SimpleConstructionScript")
+                    })
+                    .unwrap_or(false)
+        })?;
+
+    let root_node = scs_export
+        .properties
+        .iter()
+        .find(|e| e.get_name().get_content(|e| e == "RootNodes"))
+        .and_then(|e| cast!(Property, ArrayProperty, e))?
+        .value
+        .first()
+        .and_then(|e| cast!(Property, ObjectProperty, e))?
+        .value;
+    if !root_node.is_export() {
+        return None;
+    }
+    let root_node = exports
+        .get(root_node.index as usize - 1)?
+        .get_normal_export()?;
+
+    let get_node_property = |name: &str| {
+        root_node
+            .properties
+            .iter()
+            .find(|e| e.get_name().get_content(|e| e == name))
+    };
+
+    let name = get_node_property("InternalVariableName")
+        .and_then(|e| cast!(Property, NameProperty, e))?
+        .value
+        .get_owned_content();
+
+    let class_import = get_node_property("ComponentClass")
+        .and_then(|e| cast!(Property, ObjectProperty, e))
+        .and_then(|e| asset.get_import(e.value))?;
+    let class_package = asset
+        .get_import(class_import.outer_index)?
+        .object_name
+        .get_owned_content();
+
+    let template = get_node_property("ComponentTemplate")
+        .and_then(|e| cast!(Property, ObjectProperty, e))?
+        .value;
+    if !template.is_export() {
+        return None;
+    }
+    let template_name = exports
+        .get(template.index as usize - 1)?
+        .get_base_export()
+        .object_name
+        .get_owned_content();
+
+    Some(RootComponent {
+        name,
+        class_package,
+        class_name: class_import.object_name.get_owned_content(),
+        class_type_package: class_import.class_package.get_owned_content(),
+        class_type_name: class_import.class_name.get_owned_content(),
+        template_name,
+    })
+}
+
+/// Add an actor and its root component to the persistent level of a map
+fn add_level_actor<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    gen_variable: &NormalExport<PackageIndex>,
+    actor: &LevelActor,
+    root_component: &RootComponent,
+) -> Result<(), io::Error> {
+    let level_index = asset
+        .asset_data
+        .exports
+        .iter()
+        .position(|e| cast!(Export, LevelExport, e).is_some())
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Failed to find level export"))?;
+    let level = PackageIndex::new(level_index as i32 + 1);
+
+    let (blueprint_generated_class_import, default_import) =
+        add_class_imports(asset, &actor.actor)?;
+
+    let component_package_import = Import {
+        class_package: asset.add_fname("/Script/CoreUObject"),
+        class_name: asset.add_fname("Package"),
+        outer_index: PackageIndex::new(0),
+        object_name: asset.add_fname(&root_component.class_package),
+        optional: false,
+    };
+    let component_package_import = find_or_add_import(asset, component_package_import);
+
+    let component_class_import = Import {
+        class_package: asset.add_fname(&root_component.class_type_package),
+        class_name: asset.add_fname(&root_component.class_type_name),
+        outer_index: component_package_import,
+        object_name: asset.add_fname(&root_component.class_name),
+        optional: false,
+    };
+    let component_class_import = find_or_add_import(asset, component_class_import);
+
+    let component_template_import = Import {
+        class_package: asset.add_fname(&root_component.class_package),
+        class_name: asset.add_fname(&root_component.class_name),
+        outer_index: blueprint_generated_class_import,
+        object_name: asset.add_fname(&root_component.template_name),
+        optional: false,
+    };
+    let component_template_import = find_or_add_import(asset, component_template_import);
+
+    let actor_name = match &actor.name {
+        Some(name) => name.clone(),
+        None => asset
+            .get_import(blueprint_generated_class_import)
+            .map(|e| e.object_name.get_owned_content())
+            .expect("Import was just added"),
+    };
+    let mut last_actor_index = 0;
+    for export in &asset.asset_data.exports {
+        let object_name = &export.get_base_export().object_name;
+        if object_name.get_content(|e| e == actor_name.as_str())
+            && last_actor_index < object_name.get_number()
+        {
+            last_actor_index = object_name.get_number();
+        }
+    }
+
+    let actor_index = PackageIndex::new(asset.asset_data.exports.len() as i32 + 1);
+    let component_index = PackageIndex::new(asset.asset_data.exports.len() as i32 + 2);
+
+    let mut actor_export = gen_variable.clone();
+    let actor_base_export = actor_export.get_base_export_mut();
+    actor_base_export.outer_index = level;
+    actor_base_export.class_index = blueprint_generated_class_import;
+    actor_base_export.template_index = default_import;
+    actor_base_export.object_flags = EObjectFlags::RF_TRANSACTIONAL;
+    actor_base_export.serialization_before_serialization_dependencies = Vec::new();
+    actor_base_export.create_before_serialization_dependencies = Vec::from([component_index]);
+    actor_base_export.serialization_before_create_dependencies =
+        Vec::from([blueprint_generated_class_import, default_import]);
+    actor_base_export.create_before_create_dependencies = Vec::from([level]);
+    actor_base_export.object_name = asset.add_fname_with_number(&actor_name, last_actor_index + 1);
+
+    asset.add_fname("ArrayProperty");
+    let fname_object_property = asset.add_fname("ObjectProperty");
+    let fname_blueprint_created_components = asset.add_fname("BlueprintCreatedComponents");
+    actor_export.properties = Vec::from([
+        ObjectProperty {
+            name: asset.add_fname("RootComponent"),
+            ancestry: Ancestry::default(),
+            property_guid: Some(Guid::default()),
+            duplication_index: 0,
+            value: component_index,
+        }
+        .into(),
+        ArrayProperty {
+            name: fname_blueprint_created_components.clone(),
+            ancestry: Ancestry::default(),
+            property_guid: Some(Guid::default()),
+            duplication_index: 0,
+            array_type: Some(fname_object_property),
+            value: Vec::from([ObjectProperty {
+                name: fname_blueprint_created_components,
+                ancestry: Ancestry::default(),
+                property_guid: None,
+                duplication_index: 0,
+                value: component_index,
+            }
+            .into()]),
+        }
+        .into(),
+    ]);
+
+    let mut component_export = gen_variable.clone();
+    let component_base_export = component_export.get_base_export_mut();
+    component_base_export.outer_index = actor_index;
+    component_base_export.class_index = component_class_import;
+    component_base_export.template_index = component_template_import;
+    component_base_export.object_flags = EObjectFlags::RF_TRANSACTIONAL;
+    component_base_export.serialization_before_serialization_dependencies = Vec::new();
+    component_base_export.create_before_serialization_dependencies = Vec::new();
+    component_base_export.serialization_before_create_dependencies =
+        Vec::from([component_class_import, component_template_import]);
+    component_base_export.create_before_create_dependencies = Vec::from([actor_index]);
+    component_base_export.object_name = asset.add_fname(&root_component.name);
+
+    asset.add_fname("StructProperty");
+    asset.add_fname("EnumProperty");
+    component_export.properties = Vec::from([
+        vector_struct(asset, "RelativeLocation", "Vector", actor.location),
+        vector_struct(asset, "RelativeRotation", "Rotator", actor.rotation),
+        vector_struct(asset, "RelativeScale3D", "Vector", actor.scale),
+        EnumProperty {
+            name: asset.add_fname("CreationMethod"),
+            ancestry: Ancestry::default(),
+            property_guid: Some(Guid::default()),
+            duplication_index: 0,
+            enum_type: Some(asset.add_fname("EComponentCreationMethod")),
+            inner_type: None,
+            value: Some(asset.add_fname("EComponentCreationMethod::SimpleConstructionScript")),
+        }
+        .into(),
+    ]);
+
+    asset.asset_data.exports.push(actor_export.into());
+    asset.asset_data.exports.push(component_export.into());
+
+    let level_export = cast!(
+        Export,
+        LevelExport,
+        &mut asset.asset_data.exports[level_index]
+    )
+    .expect("Corrupted memory");
+    level_export.actors.push(actor_index);
+    level_export
+        .normal_export
+        .base_export
+        .create_before_serialization_dependencies
+        .push(actor_index);
+
+    Ok(())
+}
+
+/// Create a struct property holding a vector or rotator
+fn vector_struct<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    name: &str,
+    struct_type: &str,
+    [x, y, z]: [f64; 3],
+) -> Property {
+    let name = asset.add_fname(name);
+    let value = Vector::new(x.into(), y.into(), z.into());
+    let inner: Property = match struct_type {
+        "Rotator" => RotatorProperty {
+            name: name.clone(),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            value,
+        }
+        .into(),
+        _ => VectorProperty {
+            name: name.clone(),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            value,
+        }
+        .into(),
+    };
+
+    StructProperty {
+        name,
+        ancestry: Ancestry::default(),
+        struct_type: Some(asset.add_fname(struct_type)),
+        struct_guid: Some(Guid::default()),
+        property_guid: None,
+        duplication_index: 0,
+        serialize_none: true,
+        value: Vec::from([inner]),
+    }
+    .into()
+}
@@ -1,10 +1,12 @@
 #![allow(clippy::io_other_error)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{self, BufReader, Cursor, ErrorKind};
+use std::io::{self, BufReader, Cursor, ErrorKind, Read, Seek};
 use std::path::Path;
 
+use log::warn;
+
 use unreal_mod_manager::unreal_asset::reader::archive_trait::ArchiveTrait;
 use unreal_mod_manager::unreal_asset::types::PackageIndexTrait;
 use unreal_mod_manager::unreal_asset::unversioned::ancestry::Ancestry;
@@ -13,12 +15,13 @@ use uuid::Uuid;
 use unreal_mod_manager::unreal_asset::{
     cast,
     engine_version::EngineVersion,
-    exports::{Export, ExportBaseTrait, ExportNormalTrait},
+    exports::{normal_export::NormalExport, Export, ExportBaseTrait, ExportNormalTrait},
     enums::{EArrayDim, ELifetimeCondition},
     flags::{EObjectFlags, EPropertyFlags},
     properties::{
-        guid_property::GuidProperty, int_property::BoolProperty, object_property::ObjectProperty,
-        str_property::NameProperty, struct_property::StructProperty, Property, PropertyDataTrait,
+        array_property::ArrayProperty, enum_property::EnumProperty, guid_property::GuidProperty,
+        int_property::BoolProperty, object_property::ObjectProperty, str_property::NameProperty,
+        struct_property::StructProperty, Property, PropertyDataTrait,
     },
     types::PackageIndex,
     fproperty::{FGenericProperty, FObjectProperty},
@@ -31,6 +34,10 @@ use unreal_mod_manager::unreal_mod_integrator::{
 };
 use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
 
+use super::{
+    absolute_to_game, fname_matches, game_prefix_to_absolute, get_asset_names, read_pak_file,
+    MAP_PATHS,
+};
 use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
 use crate::AstroIntegratorConfig;
 
@@ -78,7 +85,64 @@ pub(crate) fn handle_linked_actor_components(
         }
     }
 
+    // wildcard keys target every matching blueprint, see WildcardTarget
+    let wildcard_keys: Vec<String> = new_components
+        .keys()
+        .filter(|e| e.contains('*'))
+        .cloned()
+        .collect();
+
+    for key in wildcard_keys {
+        let components = new_components.remove(&key).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                format!("Missing components for linked_actor_components target {key}"),
+            )
+        })?;
+        let targets = resolve_actor_targets(integrated_pak, game_paks, mod_paks, &key)?;
+        if targets.is_empty() {
+            warn!(
+                "No blueprints found for linked_actor_components target {}",
+                key
+            );
+        }
+
+        for target in targets {
+            let entry = new_components.entry(target).or_insert_with(Vec::new);
+            for component in &components {
+                if !entry.contains(component) {
+                    entry.push(component.clone());
+                }
+            }
+        }
+    }
+
+    // keys in the form of "/Game/Maps/Map.umap:ActorName" target an actor placed in a level
+    let mut level_actor_components: HashMap<String, Vec<(&str, &Vec<String>)>> = HashMap::new();
+
     for (name, components) in &new_components {
+        if let Some((map_name, actor_name)) = name.split_once(".umap:") {
+            let map_path = game_to_absolute(
+                AstroIntegratorConfig::GAME_NAME,
+                &(String::from(map_name) + ".umap"),
+            )
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid map name"))?;
+
+            if !MAP_PATHS.contains(&map_path.as_str()) {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Unsupported map {map_name} for linked_actor_components"),
+                )
+                .into());
+            }
+
+            level_actor_components
+                .entry(map_path)
+                .or_default()
+                .push((actor_name, components));
+            continue;
+        }
+
         let name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
             .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
         let mut asset = get_asset(
@@ -154,48 +218,9 @@ pub(crate) fn handle_linked_actor_components(
                 .find_import_no_index(&script_engine, &name_scs_node, &default_scs_node)
                 .expect("No default scs");
 
-            let component = Path::new(component_path_raw)
-                .file_stem()
-                .and_then(|e| e.to_str())
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid persistent actors"))?;
-
-            let (component_path_raw, component) = match component.contains('.') {
-                true => {
-                    let split: Vec<&str> = component.split('.').collect();
-                    (split[0].to_string(), &split[1][..split[1].len() - 2])
-                }
-                false => (component_path_raw.to_string(), component),
-            };
-            let component_c = String::from(component) + "_C";
-            let default_component = String::from("Default__") + component + "_C";
-
-            let package_import = Import {
-                class_package: asset.add_fname("/Script/CoreUObject"),
-                class_name: asset.add_fname("Package"),
-                outer_index: PackageIndex::new(0),
-                object_name: asset.add_fname(&component_path_raw),
-                optional: false,
-            };
-            let package_import = asset.add_import(package_import);
-
-            let blueprint_generated_class_import = Import {
-                class_package: asset.add_fname("/Script/Engine"),
-                class_name: asset.add_fname("BlueprintGeneratedClass"),
-                outer_index: package_import,
-                object_name: asset.add_fname(&component_c),
-                optional: false,
-            };
-            let blueprint_generated_class_import =
-                asset.add_import(blueprint_generated_class_import);
-
-            let default_import = Import {
-                class_package: asset.add_fname("/Game/AddMe"),
-                class_name: asset.add_fname(&component_c),
-                outer_index: package_import,
-                object_name: asset.add_fname(&default_component),
-                optional: false,
-            };
-            let default_import = asset.add_import(default_import);
+            let (component, blueprint_generated_class_import, default_import) =
+                add_component_imports(&mut asset, component_path_raw)?;
+            let component = component.as_str();
 
             // 4.23
             /*let mut component_export = component_export.clone();
@@ -435,5 +460,477 @@ pub(crate) fn handle_linked_actor_components(
         write_asset(integrated_pak, &asset, &name)
             .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
     }
+
+    for (map_path, actors) in &level_actor_components {
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            map_path,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for (actor_name, components) in actors {
+            add_level_actor_components(&mut asset, gen_variable, map_path, actor_name, components)?;
+        }
+
+        write_asset(integrated_pak, &asset, map_path)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Check if the raw data of an asset contains a name, without parsing it
+fn contains_name(data: &[u8], name: &str) -> bool {
+    data.windows(name.len()).any(|e| e == name.as_bytes())
+}
+
+/// A `linked_actor_components` key targeting several blueprints
+///
+/// Keys are either `/Game/Items/*`, for every blueprint under a path, or
+/// `/Game/Items/*:/Game/Items/ItemBase`, for the blueprints under a path that
+/// derive from a parent class, directly or through other blueprints. The
+/// parent is a blueprint package, whose class is the `_C` class, or a full
+/// object path such as `/Script/Astro.PhysicalItem` for native classes.
+struct WildcardTarget {
+    prefix: String,
+    parent: Option<(String, String)>,
+}
+
+impl WildcardTarget {
+    fn parse(key: &str) -> Option<Self> {
+        let (prefix, parent) = match key.split_once(':') {
+            Some((prefix, parent)) => (prefix, Some(parent)),
+            None => (key, None),
+        };
+        let prefix = game_prefix_to_absolute(prefix.strip_suffix('*')?)?;
+
+        let parent = match parent {
+            Some(parent) => Some(match parent.split_once('.') {
+                Some((package, class_name)) => (String::from(package), String::from(class_name)),
+                None => {
+                    let class_name = Path::new(parent).file_stem()?.to_str()?;
+                    (String::from(parent), String::from(class_name) + "_C")
+                }
+            }),
+            None => None,
+        };
+
+        Some(WildcardTarget { prefix, parent })
+    }
+}
+
+/// Resolve a wildcard target to the game paths of all matching blueprints
+///
+/// Assets are filtered on the names they contain before they are parsed, so
+/// only blueprints with a construction script that reference a matching
+/// parent package are loaded.
+fn resolve_actor_targets(
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    key: &str,
+) -> Result<Vec<String>, io::Error> {
+    let target = WildcardTarget::parse(key).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::Other,
+            format!("Invalid linked_actor_components target {key}"),
+        )
+    })?;
+    let invalid_name = || io::Error::new(ErrorKind::Other, "Invalid asset name");
+
+    let asset_names: Vec<String> = get_asset_names(integrated_pak, game_paks, mod_paks)
+        .into_iter()
+        .filter(|e| e.starts_with(&target.prefix) && e.ends_with(".uasset"))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for asset_name in asset_names {
+        let Some(data) = read_pak_file(integrated_pak, game_paks, mod_paks, &asset_name)? else {
+            continue;
+        };
+        if contains_name(&data, "SimpleConstructionScript")
+            && contains_name(&data, "BlueprintGeneratedClass")
+        {
+            candidates.push(asset_name);
+        }
+    }
+
+    let Some(parent) = target.parent else {
+        return candidates
+            .iter()
+            .map(|e| absolute_to_game(e).ok_or_else(invalid_name))
+            .collect();
+    };
+
+    // walk down the class hierarchy so that indirect children are found too,
+    // only assets referencing the package of an already found class can derive from it
+    let mut derived_classes = HashSet::from([parent]);
+    let mut blueprint_classes = HashMap::new();
+    let mut targets = Vec::new();
+    loop {
+        let mut found = false;
+        let mut remaining = Vec::new();
+        for asset_name in candidates {
+            let data = read_pak_file(integrated_pak, game_paks, mod_paks, &asset_name)?
+                .unwrap_or_default();
+            if !derived_classes
+                .iter()
+                .any(|(package, _)| contains_name(&data, package))
+            {
+                remaining.push(asset_name);
+                continue;
+            }
+
+            if !blueprint_classes.contains_key(&asset_name) {
+                let class = match get_asset(
+                    integrated_pak,
+                    game_paks,
+                    mod_paks,
+                    &asset_name,
+                    EngineVersion::VER_UE4_27,
+                ) {
+                    Ok(asset) => get_blueprint_class(&asset),
+                    Err(e) => {
+                        warn!("Failed to parse {}, skipping: {}", asset_name, e);
+                        None
+                    }
+                };
+                blueprint_classes.insert(asset_name.clone(), class);
+            }
+
+            match &blueprint_classes[&asset_name] {
+                Some((class_name, parent)) if derived_classes.contains(parent) => {
+                    let game_path = absolute_to_game(&asset_name).ok_or_else(invalid_name)?;
+                    derived_classes.insert((game_path.clone(), class_name.clone()));
+                    targets.push(game_path);
+                    found = true;
+                }
+                Some(_) => remaining.push(asset_name),
+                None => {}
+            }
+        }
+
+        if !found {
+            break;
+        }
+        candidates = remaining;
+    }
+
+    Ok(targets)
+}
+
+/// Get the class name and the package and class name of the parent of a
+/// blueprint with a construction script
+fn get_blueprint_class<C: Read + Seek>(asset: &Asset<C>) -> Option<(String, (String, String))> {
+    let has_scs = asset
+        .asset_data
+        .exports
+        .iter()
+        .filter_map(|e| e.get_normal_export())
+        .filter(|e| e.base_export.class_index.is_import())
+        .any(|e| {
+            asset
+                .get_import(e.base_export.class_index)
+                .map(|e| {
+                    e.object_name
+                        .get_content(|e| e == "SimpleConstructionScript")
+                })
+                .unwrap_or(false)
+        });
+    if !has_scs {
+        return None;
+    }
+
+    asset
+        .asset_data
+        .exports
+        .iter()
+        .filter_map(|e| cast!(Export, ClassExport, e))
+        .find_map(|class_export| {
+            let parent = asset.get_import(class_export.struct_export.super_struct)?;
+            let parent_package = asset.get_import(parent.outer_index)?;
+            Some((
+                class_export
+                    .struct_export
+                    .normal_export
+                    .base_export
+                    .object_name
+                    .get_owned_content(),
+                (
+                    parent_package.object_name.get_owned_content(),
+                    parent.object_name.get_owned_content(),
+                ),
+            ))
+        })
+}
+
+/// Add imports for a component blueprint class
+///
+/// Returns the component name, the class import and the default object import.
+pub(crate) fn add_component_imports<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    component_path_raw: &str,
+) -> Result<(String, PackageIndex, PackageIndex), io::Error> {
+    let component = Path::new(component_path_raw)
+        .file_stem()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid persistent actors"))?;
+
+    let (component_path_raw, component) = match component.contains('.') {
+        true => {
+            let split: Vec<&str> = component.split('.').collect();
+            (split[0].to_string(), &split[1][..split[1].len() - 2])
+        }
+        false => (component_path_raw.to_string(), component),
+    };
+    let component_c = String::from(component) + "_C";
+    let default_component = String::from("Default__") + component + "_C";
+
+    let package_import = Import {
+        class_package: asset.add_fname("/Script/CoreUObject"),
+        class_name: asset.add_fname("Package"),
+        outer_index: PackageIndex::new(0),
+        object_name: asset.add_fname(&component_path_raw),
+        optional: false,
+    };
+    let package_import = asset.add_import(package_import);
+
+    let blueprint_generated_class_import = Import {
+        class_package: asset.add_fname("/Script/Engine"),
+        class_name: asset.add_fname("BlueprintGeneratedClass"),
+        outer_index: package_import,
+        object_name: asset.add_fname(&component_c),
+        optional: false,
+    };
+    let blueprint_generated_class_import = asset.add_import(blueprint_generated_class_import);
+
+    let default_import = Import {
+        class_package: asset.add_fname("/Game/AddMe"),
+        class_name: asset.add_fname(&component_c),
+        outer_index: package_import,
+        object_name: asset.add_fname(&default_component),
+        optional: false,
+    };
+    let default_import = asset.add_import(default_import);
+
+    Ok((
+        String::from(component),
+        blueprint_generated_class_import,
+        default_import,
+    ))
+}
+
+/// Add imports for a blueprint class and its default object
+///
+/// The default object is imported from the class's own package, with the
+/// blueprint class as its class. Returns the class import and the default
+/// object import.
+pub(crate) fn add_class_imports<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    class_path: &str,
+) -> Result<(PackageIndex, PackageIndex), io::Error> {
+    let invalid_path = || io::Error::new(ErrorKind::Other, format!("Invalid class {class_path}"));
+    let (package_path, class_name) = match class_path.split_once('.') {
+        Some((package_path, class_name)) => (package_path, String::from(class_name)),
+        None => {
+            let class_name = Path::new(class_path)
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .ok_or_else(invalid_path)?;
+            (class_path, String::from(class_name) + "_C")
+        }
+    };
+    if !class_name.ends_with("_C") {
+        return Err(invalid_path());
+    }
+    let default_name = String::from("Default__") + &class_name;
+
+    let package_import = Import {
+        class_package: asset.add_fname("/Script/CoreUObject"),
+        class_name: asset.add_fname("Package"),
+        outer_index: PackageIndex::new(0),
+        object_name: asset.add_fname(package_path),
+        optional: false,
+    };
+    let package_import = find_or_add_import(asset, package_import);
+
+    let class_import = Import {
+        class_package: asset.add_fname("/Script/Engine"),
+        class_name: asset.add_fname("BlueprintGeneratedClass"),
+        outer_index: package_import,
+        object_name: asset.add_fname(&class_name),
+        optional: false,
+    };
+    let class_import = find_or_add_import(asset, class_import);
+
+    let default_import = Import {
+        class_package: asset.add_fname(package_path),
+        class_name: asset.add_fname(&class_name),
+        outer_index: package_import,
+        object_name: asset.add_fname(&default_name),
+        optional: false,
+    };
+    let default_import = find_or_add_import(asset, default_import);
+
+    Ok((class_import, default_import))
+}
+
+/// Find an existing import, or add it if the asset doesn't import it yet
+pub(crate) fn find_or_add_import<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    import: Import,
+) -> PackageIndex {
+    let existing = asset.imports.iter().position(|e| {
+        e.class_package == import.class_package
+            && e.class_name == import.class_name
+            && e.outer_index == import.outer_index
+            && e.object_name == import.object_name
+    });
+    match existing {
+        Some(index) => PackageIndex::new(-(index as i32) - 1),
+        None => asset.add_import(import),
+    }
+}
+
+/// Add component instances to an actor placed in a level
+fn add_level_actor_components<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    gen_variable: &NormalExport<PackageIndex>,
+    map_path: &str,
+    actor_name: &str,
+    components: &[String],
+) -> Result<(), io::Error> {
+    let actor_index = asset
+        .asset_data
+        .exports
+        .iter()
+        .position(|e| {
+            e.get_normal_export().is_some()
+                && fname_matches(&e.get_base_export().object_name, actor_name)
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                format!("Failed to find actor {actor_name} in {map_path}"),
+            )
+        })?;
+    let actor = PackageIndex::new(actor_index as i32 + 1);
+
+    for component_path_raw in components {
+        let (blueprint_generated_class_import, default_import) =
+            add_class_imports(asset, component_path_raw)?;
+        let component_c = asset
+            .get_import(blueprint_generated_class_import)
+            .map(|e| e.object_name.get_owned_content())
+            .expect("Import was just added");
+        let component = component_c.strip_suffix("_C").unwrap_or(&component_c);
+
+        let name_taken = asset.asset_data.exports.iter().any(|e| {
+            let base_export = e.get_base_export();
+            base_export.outer_index == actor
+                && base_export.object_name.get_content(|e| e == component)
+        });
+        if name_taken {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("Actor {actor_name} in {map_path} already has a component {component}"),
+            ));
+        }
+
+        let mut component_export = gen_variable.clone();
+        let component_base_export = component_export.get_base_export_mut();
+        component_base_export.outer_index = actor;
+        component_base_export.class_index = blueprint_generated_class_import;
+        component_base_export.template_index = default_import;
+        component_base_export.object_flags = EObjectFlags::RF_TRANSACTIONAL;
+        component_base_export.serialization_before_serialization_dependencies = Vec::new();
+        component_base_export.create_before_serialization_dependencies = Vec::new();
+        component_base_export.serialization_before_create_dependencies =
+            Vec::from([blueprint_generated_class_import, default_import]);
+        component_base_export.create_before_create_dependencies = Vec::from([actor]);
+        component_base_export.object_name = asset.add_fname(component);
+
+        asset.add_fname("BoolProperty");
+        asset.add_fname("EnumProperty");
+        asset.add_fname("ByteProperty");
+        component_export.properties = Vec::from([
+            BoolProperty {
+                name: asset.add_fname("bAutoActivate"),
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: true,
+            }
+            .into(),
+            // components added to a placed actor are instance components
+            EnumProperty {
+                name: asset.add_fname("CreationMethod"),
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                enum_type: Some(asset.add_fname("EComponentCreationMethod")),
+                inner_type: None,
+                value: Some(asset.add_fname("EComponentCreationMethod::Instance")),
+            }
+            .into(),
+        ]);
+
+        asset.asset_data.exports.push(component_export.into());
+        let component_index = PackageIndex::new(asset.asset_data.exports.len() as i32);
+
+        asset.add_fname("ArrayProperty");
+        let fname_object_property = asset.add_fname("ObjectProperty");
+        let fname_instance_components = asset.add_fname("InstanceComponents");
+
+        let actor_export = asset.asset_data.exports[actor_index]
+            .get_normal_export_mut()
+            .expect("Corrupted memory");
+        actor_export
+            .base_export
+            .create_before_serialization_dependencies
+            .push(component_index);
+
+        let instance_components = match actor_export
+            .properties
+            .iter()
+            .position(|e| e.get_name().get_content(|e| e == "InstanceComponents"))
+        {
+            Some(index) => index,
+            None => {
+                actor_export.properties.push(
+                    ArrayProperty {
+                        name: fname_instance_components,
+                        ancestry: Ancestry::default(),
+                        property_guid: Some(Guid::default()),
+                        duplication_index: 0,
+                        array_type: Some(fname_object_property),
+                        value: Vec::new(),
+                    }
+                    .into(),
+                );
+                actor_export.properties.len() - 1
+            }
+        };
+
+        let instance_components = cast!(
+            Property,
+            ArrayProperty,
+            &mut actor_export.properties[instance_components]
+        )
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid InstanceComponents"))?;
+        instance_components.value.push(
+            ObjectProperty {
+                name: instance_components.name.clone(),
+                ancestry: Ancestry::default(),
+                property_guid: None,
+                duplication_index: 0,
+                value: component_index,
+            }
+            .into(),
+        );
+    }
+
     Ok(())
 }
@@ -0,0 +1,438 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    engine_version::EngineVersion,
+    exports::{ExportBaseTrait, ExportNormalTrait},
+    properties::{Property, PropertyDataTrait},
+    Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::fname_matches;
+use super::properties::{
+    add_export_property, element_template, find_property, find_property_from_json,
+    find_property_mut, parse_property_path, property_from_json, PropertyType,
+};
+use crate::AstroIntegratorConfig;
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum EntryOperation {
+    /// Add the entry, or overwrite the value of an existing key
+    #[default]
+    Set,
+    /// Add the entry only if the key doesn't exist yet
+    Insert,
+    /// Remove the key
+    Remove,
+}
+
+/// A change to a single key of a map or set property
+///
+/// Keys and values are created from the first entry of the property, so
+/// struct values only need to contain the fields that differ from it. Set
+/// properties don't take a `value`. Properties that are empty or not
+/// serialized need their `property_type` to create entries from. Removing a
+/// key that is only set in the archetype records it as removed.
+#[derive(Deserialize, Serialize, Debug)]
+struct EntryEdit {
+    pub export: String,
+    pub property: String,
+    #[serde(default)]
+    pub operation: EntryOperation,
+    pub key: serde_json::Value,
+    #[serde(default)]
+    pub value: Option<serde_json::Value>,
+    #[serde(default)]
+    pub property_type: Option<PropertyType>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_map_set_entries(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    map_set_entries_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut asset_edits: HashMap<String, Vec<EntryEdit>> = HashMap::new();
+
+    for map_set_entries_map in map_set_entries_maps {
+        let map_set_entries_map: HashMap<String, Vec<EntryEdit>> =
+            serde_json::from_value(map_set_entries_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (name, edits) in map_set_entries_map {
+            asset_edits.entry(name).or_default().extend(edits);
+        }
+    }
+
+    for (name, edits) in &asset_edits {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for edit in edits {
+            let Some(export_index) = asset.asset_data.exports.iter().position(|e| {
+                e.get_normal_export().is_some()
+                    && fname_matches(&e.get_base_export().object_name, &edit.export)
+            }) else {
+                warn!("Failed to find export {} in {}", edit.export, name);
+                continue;
+            };
+
+            apply_entry_edit(&mut asset, export_index, edit).map_err(|e| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!("{name}: {}.{}: {e}", edit.export, edit.property),
+                )
+            })?;
+        }
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn apply_entry_edit<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    export_index: usize,
+    edit: &EntryEdit,
+) -> Result<(), io::Error> {
+    let segments = parse_property_path(&edit.property)?;
+    let property_not_found = || io::Error::new(ErrorKind::Other, "Failed to find property");
+    let not_map_or_set = || io::Error::new(ErrorKind::Other, "Property is not a map or set");
+
+    let exists = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .is_some();
+    if !exists {
+        let property_type = edit.property_type.as_ref().ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                "Failed to find property, give its type to add it",
+            )
+        })?;
+        add_export_property(asset, export_index, &segments, property_type)?;
+    }
+
+    let property = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .ok_or_else(property_not_found)?
+        .clone();
+    let name = property.get_name().get_owned_content();
+    let (key_type, value_type) = match edit.property_type.as_ref().and_then(|e| e.entry()) {
+        Some((key_type, value_type)) => (Some(key_type), value_type),
+        None => (None, None),
+    };
+
+    let (key_template, value_template) = match &property {
+        Property::MapProperty(map) => {
+            let first = map.value.iter().next();
+            let key = element_template(
+                asset,
+                &name,
+                first.map(|(_, key, _)| key),
+                key_type,
+                Some(map.key_type.get_owned_content()),
+            )?;
+            let value = element_template(
+                asset,
+                &name,
+                first.map(|(_, _, value)| value),
+                value_type,
+                Some(map.value_type.get_owned_content()),
+            )?;
+            (key, Some(value))
+        }
+        Property::SetProperty(set) => {
+            let key = element_template(
+                asset,
+                &name,
+                set.value.value.first(),
+                key_type,
+                set.array_type.as_ref().map(|e| e.get_owned_content()),
+            )?;
+            (key, None)
+        }
+        _ => return Err(not_map_or_set()),
+    };
+
+    // Keys are looked up with their existing object imports first, imports
+    // are only added for keys and values that are actually inserted
+    let key = match find_property_from_json(asset, &key_template, &edit.key)? {
+        Some(key) => key,
+        None if edit.operation == EntryOperation::Remove => {
+            warn!("Key {} to remove is not referenced by the asset", edit.key);
+            return Ok(());
+        }
+        None => property_from_json(asset, &key_template, &edit.key)?,
+    };
+
+    let exists = match &property {
+        Property::MapProperty(map) => map.value.get_by_key(&key).is_some(),
+        Property::SetProperty(set) => set.value.value.contains(&key),
+        _ => false,
+    };
+    if edit.operation == EntryOperation::Insert && exists {
+        warn!("Key {} already exists", edit.key);
+        return Ok(());
+    }
+
+    let value = match (&value_template, edit.operation) {
+        (Some(value_template), EntryOperation::Set | EntryOperation::Insert) => {
+            let value = edit
+                .value
+                .as_ref()
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Missing value for map entry"))?;
+            Some(property_from_json(asset, value_template, value)?)
+        }
+        _ => None,
+    };
+
+    let property = asset.asset_data.exports[export_index]
+        .get_normal_export_mut()
+        .and_then(|e| find_property_mut(&mut e.properties, &segments))
+        .ok_or_else(property_not_found)?;
+
+    // Removed keys are also recorded so that keys only set in the archetype
+    // are removed as well
+    match (property, edit.operation) {
+        (Property::MapProperty(map), EntryOperation::Remove) => {
+            map.value.remove_by_key(&key);
+            let keys_to_remove = map.keys_to_remove.get_or_insert_with(Vec::new);
+            if !keys_to_remove.contains(&key) {
+                keys_to_remove.push(key);
+            }
+        }
+        (Property::MapProperty(map), _) => {
+            let value = value
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Missing value for map entry"))?;
+            if let Some(keys_to_remove) = &mut map.keys_to_remove {
+                keys_to_remove.retain(|e| e != &key);
+            }
+            map.value.insert(key, value);
+        }
+        (Property::SetProperty(set), EntryOperation::Remove) => {
+            set.value.value.retain(|e| e != &key);
+            let removed_items = &mut set.removed_items.value;
+            if !removed_items.contains(&key) {
+                removed_items.push(key);
+            }
+        }
+        (Property::SetProperty(set), _) => {
+            set.removed_items.value.retain(|e| e != &key);
+            if !exists {
+                set.value.value.push(key);
+            }
+        }
+        _ => return Err(not_map_or_set()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use unreal_mod_manager::unreal_asset::{cast, properties::int_property::IntProperty};
+
+    use super::*;
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+
+    fn test_asset() -> (Asset<Cursor<Vec<u8>>>, usize) {
+        let asset = Asset::new(
+            Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+            Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+            EngineVersion::VER_UE4_27,
+            None,
+        )
+        .expect("Corrupted ActorTemplate");
+        let export_index = asset
+            .asset_data
+            .exports
+            .iter()
+            .position(|e| e.get_normal_export().is_some())
+            .expect("Missing normal export");
+        (asset, export_index)
+    }
+
+    fn edit(value: serde_json::Value) -> EntryEdit {
+        serde_json::from_value(value).expect("Invalid edit")
+    }
+
+    fn property<'a, C: Read + Seek>(
+        asset: &'a Asset<C>,
+        export_index: usize,
+        name: &str,
+    ) -> &'a Property {
+        asset.asset_data.exports[export_index]
+            .get_normal_export()
+            .and_then(|e| find_property(&e.properties, &parse_property_path(name).unwrap()))
+            .expect("Missing property")
+    }
+
+    fn names(properties: &[Property]) -> Vec<String> {
+        properties
+            .iter()
+            .map(|e| {
+                cast!(Property, NameProperty, e)
+                    .expect("Not a name")
+                    .value
+                    .get_owned_content()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn insert_into_unserialized_set() {
+        let (mut asset, export_index) = test_asset();
+
+        let insert = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestSet",
+            "operation": "insert",
+            "key": "A",
+            "property_type": { "set": "NameProperty" }
+        }));
+        apply_entry_edit(&mut asset, export_index, &insert).unwrap();
+        apply_entry_edit(&mut asset, export_index, &insert).unwrap();
+
+        let set = cast!(
+            Property,
+            SetProperty,
+            property(&asset, export_index, "TestSet")
+        )
+        .expect("Not a set");
+        assert_eq!(names(&set.value.value), ["A"]);
+        assert!(set.removed_items.value.is_empty());
+    }
+
+    #[test]
+    fn remove_from_set_records_removed_item() {
+        let (mut asset, export_index) = test_asset();
+
+        let remove = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestSet",
+            "operation": "remove",
+            "key": "A",
+            "property_type": { "set": "NameProperty" }
+        }));
+        apply_entry_edit(&mut asset, export_index, &remove).unwrap();
+
+        let set = cast!(
+            Property,
+            SetProperty,
+            property(&asset, export_index, "TestSet")
+        )
+        .expect("Not a set");
+        assert!(set.value.value.is_empty());
+        assert_eq!(names(&set.removed_items.value), ["A"]);
+    }
+
+    #[test]
+    fn set_and_remove_map_entries() {
+        let (mut asset, export_index) = test_asset();
+        let property_type = serde_json::json!({ "map": "NameProperty", "value": "IntProperty" });
+
+        for (key, value) in [("A", 1), ("B", 2), ("A", 3)] {
+            let set = edit(serde_json::json!({
+                "export": "Test",
+                "property": "TestMap",
+                "key": key,
+                "value": value,
+                "property_type": property_type
+            }));
+            apply_entry_edit(&mut asset, export_index, &set).unwrap();
+        }
+        let insert = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestMap",
+            "operation": "insert",
+            "key": "B",
+            "value": 4
+        }));
+        apply_entry_edit(&mut asset, export_index, &insert).unwrap();
+        let remove = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestMap",
+            "operation": "remove",
+            "key": "C"
+        }));
+        apply_entry_edit(&mut asset, export_index, &remove).unwrap();
+
+        let map = cast!(
+            Property,
+            MapProperty,
+            property(&asset, export_index, "TestMap")
+        )
+        .expect("Not a map");
+        let entries = map
+            .value
+            .iter()
+            .map(|(_, key, value)| {
+                (
+                    names(std::slice::from_ref(key)).remove(0),
+                    cast!(Property, IntProperty, value)
+                        .expect("Not an int")
+                        .value,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [(String::from("A"), 3), (String::from("B"), 2)]);
+        assert_eq!(
+            names(map.keys_to_remove.as_deref().unwrap_or_default()),
+            ["C"]
+        );
+    }
+
+    #[test]
+    fn map_entry_requires_value() {
+        let (mut asset, export_index) = test_asset();
+
+        let set = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestMap",
+            "key": "A",
+            "property_type": { "map": "NameProperty", "value": "IntProperty" }
+        }));
+        assert!(apply_entry_edit(&mut asset, export_index, &set).is_err());
+    }
+
+    #[test]
+    fn unserialized_property_requires_type() {
+        let (mut asset, export_index) = test_asset();
+
+        let insert = edit(serde_json::json!({
+            "export": "Test",
+            "property": "TestSet",
+            "operation": "insert",
+            "key": "A"
+        }));
+        assert!(apply_entry_edit(&mut asset, export_index, &insert).is_err());
+    }
+}
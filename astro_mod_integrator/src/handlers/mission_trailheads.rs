@@ -1,9 +1,12 @@
 #![allow(clippy::io_other_error)]
 
 use std::fs::File;
-use std::io::{self, BufReader, ErrorKind};
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
 use std::path::Path;
 
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
 use unreal_mod_manager::unreal_asset::types::PackageIndexTrait;
 use unreal_mod_manager::unreal_asset::unversioned::ancestry::Ancestry;
 use unreal_mod_manager::unreal_asset::{
@@ -12,16 +15,97 @@ use unreal_mod_manager::unreal_asset::{
     exports::{Export, ExportNormalTrait},
     properties::{object_property::ObjectProperty, Property},
     types::PackageIndex,
-    Import,
+    Asset, Import,
 };
-use unreal_mod_manager::unreal_helpers::Guid;
+use unreal_mod_manager::unreal_helpers::{game_to_absolute, Guid};
 use unreal_mod_manager::unreal_mod_integrator::{
     helpers::{get_asset, write_asset},
-    Error,
+    Error, IntegratorConfig,
 };
 use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
 
-use super::MAP_PATHS;
+use super::{find_mod_id, get_asset_names, GameMap, GameMode, GAME_MAPS, MAP_PATHS};
+use crate::AstroIntegratorConfig;
+
+/// Class and property name of the mission list
+const DEFAULT_REGISTRY_CLASS: &str = "AstroSettings";
+const DEFAULT_REGISTRY_PROPERTY: &str = "MissionData";
+
+/// A mission list to use when a map has no `AstroSettings` `MissionData`,
+/// for game builds that moved the list to another class
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+struct MissionRegistry {
+    pub class: String,
+    pub property: String,
+}
+
+/// Trailheads of a mod, either a plain list or a list along with the
+/// fallback registries to search
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+enum MissionTrailheads {
+    Trailheads(Vec<TrailheadEntry>),
+    WithRegistries {
+        trailheads: Vec<TrailheadEntry>,
+        #[serde(default)]
+        fallback_registries: Vec<MissionRegistry>,
+    },
+}
+
+/// A mission trailhead entry, either a plain mission data asset path or a [`Trailhead`]
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+enum TrailheadEntry {
+    Path(String),
+    Trailhead(Trailhead),
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Trailhead {
+    pub path: String,
+    /// Maps to add the trailhead to, e.g. `/Game/Maps/Staging_T2`, all maps
+    /// if not specified
+    #[serde(default)]
+    pub maps: Option<Vec<String>>,
+    /// Game modes to add the trailhead to, all game modes if not specified
+    #[serde(default)]
+    pub game_modes: Option<Vec<GameMode>>,
+    /// Remove an existing mission from the maps instead of adding one
+    #[serde(default)]
+    pub remove: bool,
+    /// Id of the mod that added the trailhead, for warnings
+    #[serde(skip)]
+    pub mod_id: Option<String>,
+}
+
+impl From<TrailheadEntry> for Trailhead {
+    fn from(entry: TrailheadEntry) -> Self {
+        match entry {
+            TrailheadEntry::Path(path) => Trailhead {
+                path,
+                maps: None,
+                game_modes: None,
+                remove: false,
+                mod_id: None,
+            },
+            TrailheadEntry::Trailhead(trailhead) => trailhead,
+        }
+    }
+}
+
+impl Trailhead {
+    fn applies_to(&self, game_map: &GameMap) -> bool {
+        self.maps
+            .as_ref()
+            .map(|e| e.iter().any(|e| e == game_map.path))
+            .unwrap_or(true)
+            && self
+                .game_modes
+                .as_ref()
+                .map(|e| e.contains(&game_map.game_mode))
+                .unwrap_or(true)
+    }
+}
 
 #[allow(clippy::ptr_arg)]
 pub(crate) fn handle_mission_trailheads(
@@ -31,7 +115,76 @@ pub(crate) fn handle_mission_trailheads(
     mod_paks: &mut Vec<PakReader<BufReader<File>>>,
     trailhead_arrays: &Vec<serde_json::Value>,
 ) -> Result<(), Error> {
-    for map_path in MAP_PATHS {
+    let mut trailheads = Vec::new();
+    let mut registries = Vec::from([MissionRegistry {
+        class: String::from(DEFAULT_REGISTRY_CLASS),
+        property: String::from(DEFAULT_REGISTRY_PROPERTY),
+    }]);
+    for trailheads_array in trailhead_arrays {
+        let mod_trailheads: MissionTrailheads = serde_json::from_value(trailheads_array.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, format!("Invalid trailheads: {e}")))?;
+        let mod_trailheads = match mod_trailheads {
+            MissionTrailheads::Trailheads(trailheads) => trailheads,
+            MissionTrailheads::WithRegistries {
+                trailheads,
+                fallback_registries,
+            } => {
+                for registry in fallback_registries {
+                    if !registries.contains(&registry) {
+                        registries.push(registry);
+                    }
+                }
+                trailheads
+            }
+        };
+        let mod_id = find_mod_id(mod_paks, "mission_trailheads", trailheads_array);
+        trailheads.extend(mod_trailheads.into_iter().map(|e| Trailhead {
+            mod_id: mod_id.clone(),
+            ..Trailhead::from(e)
+        }));
+    }
+
+    for trailhead in &mut trailheads {
+        for map_name in trailhead.maps.iter_mut().flatten() {
+            *map_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, map_name)
+                .filter(|e| MAP_PATHS.contains(&e.as_str()))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "Unsupported map {map_name} for trailhead {}",
+                            trailhead.path
+                        ),
+                    )
+                })?;
+        }
+    }
+
+    // a trailhead pointing to a missing asset would leave a dangling import in every map
+    let asset_names = get_asset_names(integrated_pak, game_paks, mod_paks);
+    trailheads.retain(|trailhead| {
+        if trailhead.remove {
+            return true;
+        }
+
+        let exists = game_to_absolute(AstroIntegratorConfig::GAME_NAME, &trailhead.path)
+            .map(|e| asset_names.contains(&e))
+            .unwrap_or(false);
+        if !exists {
+            warn!(
+                "Mission data asset {} of mod {} not found in game or mod paks, skipping trailhead",
+                trailhead.path,
+                trailhead.mod_id.as_deref().unwrap_or("unknown")
+            );
+        }
+        exists
+    });
+
+    let mut missing_maps = Vec::new();
+    let mut applicable_maps = 0;
+
+    for game_map in &GAME_MAPS {
+        let map_path = game_map.path;
         let mut asset = get_asset(
             integrated_pak,
             game_paks,
@@ -40,55 +193,71 @@ pub(crate) fn handle_mission_trailheads(
             EngineVersion::VER_UE4_27,
         )?;
 
-        let mut trailheads = Vec::new();
-        for trailheads_array in trailhead_arrays {
-            let trailheads_array = trailheads_array
-                .as_array()
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid trailheads"))?;
-            for trailhead in trailheads_array {
-                let trailhead = trailhead
-                    .as_str()
-                    .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid trailheads"))?;
-                trailheads.push(trailhead);
-            }
+        let map_trailheads: Vec<&Trailhead> = trailheads
+            .iter()
+            .filter(|e| e.applies_to(game_map))
+            .collect();
+        if !map_trailheads.is_empty() {
+            applicable_maps += 1;
         }
 
-        let mut mission_data_export_index = None;
-        let mut mission_data_property_index = None;
-
-        for i in 0..asset.asset_data.exports.len() {
-            let export = &asset.asset_data.exports[i];
-            if let Some(normal_export) = export.get_normal_export() {
-                if normal_export.base_export.class_index.is_import() {
-                    let import = asset
-                        .get_import(normal_export.base_export.class_index)
-                        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid import"))?;
-                    if import.object_name.get_content(|e| e == "AstroSettings") {
-                        for j in 0..normal_export.properties.len() {
-                            let property = &normal_export.properties[j];
-                            if let Some(array_property) = cast!(Property, ArrayProperty, property) {
-                                if array_property.name.get_content(|e| e == "MissionData")
-                                    && array_property
-                                        .array_type
-                                        .as_ref()
-                                        .map(|e| e.get_content(|e| e == "ObjectProperty"))
-                                        .unwrap_or(false)
-                                {
-                                    mission_data_export_index = Some(i);
-                                    mission_data_property_index = Some(j);
-                                    break;
-                                }
-                            }
-                        }
-                    }
+        if let Some((mission_data_export_index, mission_data_property_index)) =
+            find_mission_data(&asset, map_path, &registries)?
+        {
+            let mut removed_imports = Vec::new();
+            for trailhead in map_trailheads.iter().filter(|e| e.remove) {
+                let imports = find_mission_imports(&asset, &trailhead.path);
+                if imports.is_empty() {
+                    warn!("Failed to find mission {} in {}", trailhead.path, map_path);
                 }
+                removed_imports.extend(imports);
             }
-        }
 
-        if let (Some(mission_data_export_index), Some(mission_data_property_index)) =
-            (mission_data_export_index, mission_data_property_index)
-        {
-            for trailhead in trailheads {
+            let mission_data_export = cast!(
+                Export,
+                NormalExport,
+                &mut asset.asset_data.exports[mission_data_export_index]
+            )
+            .expect("Corrupted memory");
+            let mission_data_property = cast!(
+                Property,
+                ArrayProperty,
+                &mut mission_data_export.properties[mission_data_property_index]
+            )
+            .expect("Corrupted memory");
+            mission_data_property.value.retain(|e| {
+                cast!(Property, ObjectProperty, e)
+                    .map(|e| !removed_imports.contains(&e.value))
+                    .unwrap_or(true)
+            });
+
+            for trailhead in map_trailheads.iter().filter(|e| !e.remove) {
+                let trailhead = trailhead.path.as_str();
+
+                let existing_imports = find_mission_imports(&asset, trailhead);
+                let mission_data_property = cast!(
+                    Export,
+                    NormalExport,
+                    &asset.asset_data.exports[mission_data_export_index]
+                )
+                .and_then(|e| {
+                    cast!(
+                        Property,
+                        ArrayProperty,
+                        &e.properties[mission_data_property_index]
+                    )
+                })
+                .expect("Corrupted memory");
+                if mission_data_property
+                    .value
+                    .iter()
+                    .filter_map(|e| cast!(Property, ObjectProperty, e))
+                    .any(|e| existing_imports.contains(&e.value))
+                {
+                    debug!("Mission {} already present in {}", trailhead, map_path);
+                    continue;
+                }
+
                 let soft_class_name = Path::new(trailhead)
                     .file_stem()
                     .and_then(|e| e.to_str())
@@ -134,11 +303,107 @@ pub(crate) fn handle_mission_trailheads(
                 };
                 mission_data_property.value.push(property.into());
             }
+        } else if !map_trailheads.is_empty() {
+            warn!("Failed to find a mission registry in {}", map_path);
+            missing_maps.push(map_path);
         }
 
         write_asset(integrated_pak, &asset, &String::from(map_path))
             .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
     }
 
+    if !missing_maps.is_empty() && missing_maps.len() == applicable_maps {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            format!(
+                "Failed to find a mission registry in {}",
+                missing_maps.join(", ")
+            ),
+        )
+        .into());
+    }
+
     Ok(())
 }
+
+/// Find the mission list export and property index in a map
+///
+/// Registries are searched in order, the first one is the game's own.
+fn find_mission_data<C: Read + Seek>(
+    asset: &Asset<C>,
+    map_path: &str,
+    registries: &[MissionRegistry],
+) -> Result<Option<(usize, usize)>, io::Error> {
+    for (registry_index, registry) in registries.iter().enumerate() {
+        for i in 0..asset.asset_data.exports.len() {
+            let Some(normal_export) = asset.asset_data.exports[i].get_normal_export() else {
+                continue;
+            };
+
+            if !normal_export.base_export.class_index.is_import() {
+                continue;
+            }
+            let import = asset
+                .get_import(normal_export.base_export.class_index)
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid import"))?;
+            if !import
+                .object_name
+                .get_content(|e| e == registry.class.as_str())
+            {
+                continue;
+            }
+
+            for j in 0..normal_export.properties.len() {
+                let property = &normal_export.properties[j];
+                if let Some(array_property) = cast!(Property, ArrayProperty, property) {
+                    if array_property
+                        .name
+                        .get_content(|e| e == registry.property.as_str())
+                        && array_property
+                            .array_type
+                            .as_ref()
+                            .map(|e| e.get_content(|e| e == "ObjectProperty"))
+                            .unwrap_or(false)
+                    {
+                        if registry_index > 0 {
+                            warn!(
+                                "Using fallback mission registry {}.{} in {}",
+                                registry.class, registry.property, map_path
+                            );
+                        }
+                        return Ok(Some((i, j)));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Find mission data asset imports for a mission data asset path
+///
+/// Imports are matched on both their package and object name, missions with
+/// the same name in different packages are different missions.
+fn find_mission_imports<C: Read + Seek>(asset: &Asset<C>, path: &str) -> Vec<PackageIndex> {
+    let Some(mission_name) = Path::new(path).file_stem().and_then(|e| e.to_str()) else {
+        return Vec::new();
+    };
+
+    asset
+        .imports
+        .iter()
+        .enumerate()
+        .filter(|(_, import)| {
+            import
+                .class_name
+                .get_content(|e| e == "AstroMissionDataAsset")
+                && import.object_name.get_content(|e| e == mission_name)
+                && asset
+                    .get_import(import.outer_index)
+                    .map(|e| e.object_name.get_content(|e| e == path))
+                    .unwrap_or(false)
+        })
+        .map(|(i, _)| PackageIndex::new(-(i as i32) - 1))
+        .collect()
+}
@@ -1,19 +1,168 @@
 #![allow(clippy::io_other_error)]
 
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+use std::path::Path;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use unreal_mod_manager::unreal_asset::types::fname::FName;
+use unreal_mod_manager::unreal_mod_integrator::IntegratorConfig;
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use crate::AstroIntegratorConfig;
+
+pub(crate) mod asset_redirects;
 pub(crate) mod biome_placement_modifiers;
+pub(crate) mod blueprint_parents;
+pub(crate) mod cdo_overrides;
+pub(crate) mod configured_persistent_actors;
+pub(crate) mod crafting_recipes;
+pub(crate) mod data_table_rows;
+mod game_maps;
 pub(crate) mod item_list_entries;
+pub(crate) mod level_actors;
 pub(crate) mod linked_actor_components;
+pub(crate) mod map_set_entries;
 pub(crate) mod mission_trailheads;
+pub(crate) mod planet_voxel_properties;
+pub(crate) mod properties;
+pub(crate) mod property_patches;
+pub(crate) mod research_items;
+pub(crate) mod soft_reference_arrays;
+pub(crate) mod string_table_entries;
+
+pub(crate) use game_maps::{GameMap, GameMode, GAME_MAPS, MAP_PATHS};
 
 lazy_static! {
     static ref GAME_REGEX: Regex = Regex::new(r"^/Game/").unwrap();
 }
 
-pub(crate) static MAP_PATHS: [&str; 3] = [
-    "Astro/Content/Maps/Staging_T2.umap",
-    "Astro/Content/Maps/Staging_T2_PackedPlanets_Switch.umap",
-    "Astro/Content/U32_Expansion/U32_Expansion.umap"
-];
+impl GameMap {
+    /// Check if a planet is present in this map
+    pub(crate) fn contains_planet(&self, planet: &str) -> bool {
+        self.planets.contains(&planet)
+    }
+
+    /// Check if any planet matching a pattern is present in this map
+    pub(crate) fn has_matching_planet(&self, pattern: &str) -> bool {
+        self.planets.iter().any(|e| matches_wildcard(pattern, e))
+    }
+}
+
+/// Check if an FName matches an export name as it is displayed, e.g. `Actor_2`
+pub(crate) fn fname_matches(name: &FName, expected: &str) -> bool {
+    let number = name.get_number();
+    name.get_content(|content| match number {
+        0 => content == expected,
+        _ => format!("{content}_{}", number - 1) == expected,
+    })
+}
+
+/// Check if a name matches a pattern, where a trailing `*` matches any suffix
+pub(crate) fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Convert a game path prefix such as `/Game/Items/` to an absolute path prefix
+pub(crate) fn game_prefix_to_absolute(prefix: &str) -> Option<String> {
+    if !GAME_REGEX.is_match(prefix) {
+        return None;
+    }
+
+    Some(
+        GAME_REGEX
+            .replace(
+                prefix,
+                format!("{}/Content/", AstroIntegratorConfig::GAME_NAME),
+            )
+            .to_string(),
+    )
+}
+
+/// Convert an absolute asset path such as `Astro/Content/Items/Item.uasset` to a game path
+pub(crate) fn absolute_to_game(path: &str) -> Option<String> {
+    let path = path.strip_prefix(&format!("{}/Content/", AstroIntegratorConfig::GAME_NAME))?;
+    let path = Path::new(path).with_extension("");
+
+    path.to_str()
+        .map(|e| String::from("/Game/") + &e.replace('\\', "/"))
+}
+
+/// Get the names of all assets in the integrated pak, game paks and mod paks
+pub(crate) fn get_asset_names(
+    integrated_pak: &PakMemory,
+    game_paks: &[PakReader<BufReader<File>>],
+    mod_paks: &[PakReader<BufReader<File>>],
+) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+
+    names.extend(integrated_pak.get_entry_names().into_iter().cloned());
+    for pak in game_paks.iter().chain(mod_paks) {
+        names.extend(pak.get_entry_names().into_iter().cloned());
+    }
+
+    names
+}
+
+/// Find the id of the mod whose metadata has `value` under `key` in its
+/// integrator section
+///
+/// Handlers only receive the values of each mod, this maps a value back to
+/// the mod it came from for warnings.
+pub(crate) fn find_mod_id(
+    mod_paks: &mut [PakReader<BufReader<File>>],
+    key: &str,
+    value: &serde_json::Value,
+) -> Option<String> {
+    let metadata_name = String::from("metadata.json");
+
+    for pak in mod_paks.iter_mut() {
+        if !pak.contains_entry(&metadata_name) {
+            continue;
+        }
+        let Ok(data) = pak.read_entry(&metadata_name) else {
+            continue;
+        };
+        let Ok(metadata) = serde_json::from_slice::<serde_json::Value>(&data) else {
+            continue;
+        };
+
+        if metadata.get("integrator").and_then(|e| e.get(key)) == Some(value) {
+            return metadata
+                .get("mod_id")
+                .and_then(|e| e.as_str())
+                .map(String::from);
+        }
+    }
+
+    None
+}
+
+/// Read a raw file from the integrated pak, mod paks or game paks, in that order
+pub(crate) fn read_pak_file(
+    integrated_pak: &PakMemory,
+    game_paks: &mut [PakReader<BufReader<File>>],
+    mod_paks: &mut [PakReader<BufReader<File>>],
+    name: &String,
+) -> Result<Option<Vec<u8>>, io::Error> {
+    if let Some(data) = integrated_pak.get_entry(name) {
+        return Ok(Some(data.clone()));
+    }
+
+    for pak in mod_paks.iter_mut().chain(game_paks.iter_mut()) {
+        if pak.contains_entry(name) {
+            return pak
+                .read_entry(name)
+                .map(Some)
+                .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,197 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::ExportNormalTrait,
+    properties::{Property, PropertyDataTrait},
+    Asset,
+};
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::biome_placement_modifiers::{
+    filter_voxel_exports, get_voxel_exports, has_matching_name, BiomeType,
+};
+use super::properties::{set_export_property, PropertyType};
+use super::{GameMap, GAME_MAPS};
+
+/// A set of property values for planets, biomes or biome layers
+///
+/// Without a `biome_name` the properties are set on the planet voxel export
+/// itself, without a `layer_name` on the biome struct. Names may end with `*`
+/// to match every planet, biome or layer starting with the given prefix.
+/// Properties that are still at their default value are not serialized, they
+/// are only added if their type is given in `property_types`.
+#[derive(Deserialize, Serialize, Debug)]
+struct VoxelPropertyEdit {
+    pub planet_type: String,
+    #[serde(default)]
+    pub biome_type: Option<BiomeType>,
+    #[serde(default)]
+    pub biome_name: Option<String>,
+    #[serde(default)]
+    pub layer_name: Option<String>,
+    /// Dotted property paths mapped to their new values
+    pub properties: BTreeMap<String, serde_json::Value>,
+    /// Dotted property paths mapped to their types
+    #[serde(default)]
+    pub property_types: BTreeMap<String, PropertyType>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_planet_voxel_properties(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    voxel_property_edits: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut edits = Vec::new();
+
+    for mod_edits in voxel_property_edits {
+        let mod_edits: Vec<VoxelPropertyEdit> = serde_json::from_value(mod_edits.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for edit in &mod_edits {
+            if edit.biome_name.is_some() && edit.biome_type.is_none() {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Missing biome_type for biome {:?}", edit.biome_name),
+                )
+                .into());
+            }
+            if edit.layer_name.is_some() && edit.biome_name.is_none() {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Missing biome_name for layer {:?}", edit.layer_name),
+                )
+                .into());
+            }
+        }
+
+        edits.extend(mod_edits);
+    }
+
+    for game_map in &GAME_MAPS {
+        let map_path = game_map.path;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &map_path.to_string(),
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let voxel_exports = get_voxel_exports(&asset)?;
+
+        for edit in &edits {
+            let targets = get_edit_targets(&asset, &voxel_exports, game_map, edit)?;
+
+            for (export_index, path_prefix) in targets {
+                for (property_path, value) in &edit.properties {
+                    set_export_property(
+                        &mut asset,
+                        export_index,
+                        &(path_prefix.clone() + property_path),
+                        value,
+                        edit.property_types.get(property_path),
+                    )?;
+                }
+            }
+        }
+
+        write_asset(integrated_pak, &asset, &map_path.to_string())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Resolve an edit to voxel export indices and the property path prefix of each target
+fn get_edit_targets<C: Read + Seek>(
+    asset: &Asset<C>,
+    voxel_exports: &BTreeMap<String, usize>,
+    game_map: &GameMap,
+    edit: &VoxelPropertyEdit,
+) -> Result<Vec<(usize, String)>, io::Error> {
+    let map_path = game_map.path;
+    let mut targets = Vec::new();
+
+    for (voxels_name, export_index) in
+        filter_voxel_exports(voxel_exports, game_map, &edit.planet_type)
+    {
+        let (Some(biome_type), Some(biome_name)) = (&edit.biome_type, &edit.biome_name) else {
+            targets.push((export_index, String::new()));
+            continue;
+        };
+
+        let biome_property_name = biome_type.property_name();
+        let export = asset.asset_data.exports[export_index]
+            .get_normal_export()
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
+
+        let Some(biome_property) = export
+            .properties
+            .iter()
+            .find(|e| e.get_name().get_content(|e| e == biome_property_name))
+            .and_then(|e| cast!(Property, ArrayProperty, e))
+        else {
+            warn!(
+                "Failed to find biome type {} for {} in {}",
+                biome_property_name, voxels_name, map_path
+            );
+            continue;
+        };
+
+        for (i, biome) in biome_property.value.iter().enumerate() {
+            let Some(biome) = cast!(Property, StructProperty, biome) else {
+                continue;
+            };
+            if !has_matching_name(&biome.value, biome_name) {
+                continue;
+            }
+
+            let biome_path = format!("{biome_property_name}[{i}].");
+            let Some(layer_name) = &edit.layer_name else {
+                targets.push((export_index, biome_path));
+                continue;
+            };
+
+            let layers = biome
+                .value
+                .iter()
+                .find(|e| e.get_name().get_content(|e| e == "Layers"))
+                .and_then(|e| cast!(Property, ArrayProperty, e))
+                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
+
+            for (j, layer) in layers.value.iter().enumerate() {
+                if cast!(Property, StructProperty, layer)
+                    .map(|e| has_matching_name(&e.value, layer_name))
+                    .unwrap_or(false)
+                {
+                    targets.push((export_index, format!("{biome_path}Layers[{j}].")));
+                }
+            }
+        }
+    }
+
+    if targets.is_empty() && game_map.has_matching_planet(&edit.planet_type) {
+        warn!(
+            "Failed to find planet {} biome {:?} layer {:?} in {}",
+            edit.planet_type, edit.biome_name, edit.layer_name, map_path
+        );
+    }
+
+    Ok(targets)
+}
@@ -0,0 +1,1106 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Seek};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::unversioned::ancestry::Ancestry;
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    containers::indexed_map::IndexedMap,
+    exports::ExportNormalTrait,
+    flags::EObjectFlags,
+    properties::{
+        array_property::ArrayProperty,
+        int_property::{BoolProperty, DoubleProperty, FloatProperty, Int64Property, IntProperty},
+        map_property::MapProperty,
+        object_property::ObjectProperty,
+        set_property::SetProperty,
+        str_property::{NameProperty, StrProperty},
+        struct_property::StructProperty,
+        Property, PropertyDataTrait,
+    },
+    types::PackageIndex,
+    Asset, Import,
+};
+use unreal_mod_manager::unreal_helpers::Guid;
+
+/// A single segment of a property path such as `Layers[2].Material`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+    Name(String),
+    Index(usize),
+}
+
+/// The type of a property that is not serialized in an asset
+///
+/// Cooked assets leave out properties that are still at their default value,
+/// so they have to be created from their type before they can be set. Types
+/// are either a property type name, e.g. `"IntProperty"`, an array with its
+/// element type, e.g. `{ "array": "ObjectProperty" }`, a map with its key and
+/// value types, e.g. `{ "map": "NameProperty", "value": "IntProperty" }`, a
+/// set with its element type, e.g. `{ "set": "NameProperty" }`, or a struct
+/// with the types of the fields to set, e.g.
+/// `{ "struct_type": "ItemRecipeIngredient", "fields": { "Count": "IntProperty" } }`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum PropertyType {
+    Simple(String),
+    Array {
+        array: Box<PropertyType>,
+    },
+    Map {
+        map: Box<PropertyType>,
+        value: Box<PropertyType>,
+    },
+    Set {
+        set: Box<PropertyType>,
+    },
+    Struct {
+        #[serde(default)]
+        struct_type: Option<String>,
+        fields: BTreeMap<String, PropertyType>,
+    },
+}
+
+impl PropertyType {
+    fn type_name(&self) -> &str {
+        match self {
+            PropertyType::Simple(type_name) => type_name,
+            PropertyType::Array { .. } => "ArrayProperty",
+            PropertyType::Map { .. } => "MapProperty",
+            PropertyType::Set { .. } => "SetProperty",
+            PropertyType::Struct { .. } => "StructProperty",
+        }
+    }
+
+    /// Type of the elements of an array type
+    pub(crate) fn element(&self) -> Option<&PropertyType> {
+        match self {
+            PropertyType::Array { array } => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Types of the keys and values of a map type, or of the elements of a set
+    /// type
+    pub(crate) fn entry(&self) -> Option<(&PropertyType, Option<&PropertyType>)> {
+        match self {
+            PropertyType::Map { map, value } => Some((map, Some(value))),
+            PropertyType::Set { set } => Some((set, None)),
+            _ => None,
+        }
+    }
+
+    /// Type of a field of a struct type
+    pub(crate) fn field(&self, name: &str) -> Option<&PropertyType> {
+        match self {
+            PropertyType::Struct { fields, .. } => fields.get(name),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a dotted property path, e.g. `SurfaceBiomes[0].Layers[2].Material`
+pub(crate) fn parse_property_path(path: &str) -> Result<Vec<PathSegment>, io::Error> {
+    let invalid_path = || io::Error::new(ErrorKind::Other, format!("Invalid property path {path}"));
+
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (name, mut indices) = match part.split_once('[') {
+            Some((name, indices)) => (name, Some(indices)),
+            None => (part, None),
+        };
+        if name.is_empty() {
+            return Err(invalid_path());
+        }
+        segments.push(PathSegment::Name(String::from(name)));
+
+        while let Some(rest) = indices {
+            let (index, rest) = rest.split_once(']').ok_or_else(invalid_path)?;
+            let index = index.parse::<usize>().map_err(|_| invalid_path())?;
+            segments.push(PathSegment::Index(index));
+
+            indices = match rest {
+                "" => None,
+                _ => Some(rest.strip_prefix('[').ok_or_else(invalid_path)?),
+            };
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Find a property by path inside a list of properties
+pub(crate) fn find_property<'a>(
+    properties: &'a [Property],
+    path: &[PathSegment],
+) -> Option<&'a Property> {
+    let (PathSegment::Name(name), mut rest) = path.split_first()? else {
+        return None;
+    };
+
+    let mut property = properties
+        .iter()
+        .find(|e| e.get_name().get_content(|e| e == name.as_str()))?;
+    while let Some((PathSegment::Index(index), next)) = rest.split_first() {
+        property = cast!(Property, ArrayProperty, property)?
+            .value
+            .get(*index)?;
+        rest = next;
+    }
+
+    match rest.is_empty() {
+        true => Some(property),
+        false => find_property(&cast!(Property, StructProperty, property)?.value, rest),
+    }
+}
+
+/// Find a property by path inside a list of properties
+pub(crate) fn find_property_mut<'a>(
+    properties: &'a mut [Property],
+    path: &[PathSegment],
+) -> Option<&'a mut Property> {
+    let (PathSegment::Name(name), mut rest) = path.split_first()? else {
+        return None;
+    };
+
+    let mut property = properties
+        .iter_mut()
+        .find(|e| e.get_name().get_content(|e| e == name.as_str()))?;
+    while let Some((PathSegment::Index(index), next)) = rest.split_first() {
+        property = cast!(Property, ArrayProperty, property)?
+            .value
+            .get_mut(*index)?;
+        rest = next;
+    }
+
+    match rest.is_empty() {
+        true => Some(property),
+        false => find_property_mut(&mut cast!(Property, StructProperty, property)?.value, rest),
+    }
+}
+
+/// Create a property of the same type as `template` from a JSON value
+///
+/// Struct values only need to contain the fields that should be changed.
+pub(crate) fn property_from_json<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    template: &Property,
+    value: &serde_json::Value,
+) -> Result<Property, io::Error> {
+    typed_property_from_json(asset, template, value, None)
+}
+
+/// Create a property of the same type as `template` from a JSON value
+///
+/// `property_type` describes the template, it is used to create struct
+/// fields that are not serialized in the template and elements of empty
+/// arrays.
+pub(crate) fn typed_property_from_json<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    template: &Property,
+    value: &serde_json::Value,
+    property_type: Option<&PropertyType>,
+) -> Result<Property, io::Error> {
+    Ok(
+        convert_property(asset, template, value, property_type, true)?
+            .expect("Object references are always added"),
+    )
+}
+
+/// Create a property of the same type as `template` from a JSON value to
+/// compare it against existing properties
+///
+/// Object references are only resolved to existing imports, no imports are
+/// added. Returns `None` if a referenced object is not imported, as no
+/// property in the asset can be equal to the value then.
+pub(crate) fn find_property_from_json<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    template: &Property,
+    value: &serde_json::Value,
+) -> Result<Option<Property>, io::Error> {
+    convert_property(asset, template, value, None, false)
+}
+
+fn convert_property<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    template: &Property,
+    value: &serde_json::Value,
+    property_type: Option<&PropertyType>,
+    add_imports: bool,
+) -> Result<Option<Property>, io::Error> {
+    let invalid_value = || {
+        io::Error::new(
+            ErrorKind::Other,
+            format!(
+                "Invalid value {} for property {}",
+                value,
+                template.get_name().get_owned_content()
+            ),
+        )
+    };
+
+    let mut property = template.clone();
+    match &mut property {
+        Property::BoolProperty(property) => {
+            property.value = value.as_bool().ok_or_else(invalid_value)?;
+        }
+        Property::Int8Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::Int16Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::IntProperty(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::Int64Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::UInt16Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::UInt32Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::UInt64Property(property) => {
+            property.value = json_to_int(value).ok_or_else(invalid_value)?;
+        }
+        Property::FloatProperty(property) => {
+            property.value = (value.as_f64().ok_or_else(invalid_value)? as f32).into();
+        }
+        Property::DoubleProperty(property) => {
+            property.value = value.as_f64().ok_or_else(invalid_value)?.into();
+        }
+        Property::StrProperty(property) => {
+            property.value = match value {
+                serde_json::Value::Null => None,
+                _ => Some(String::from(value.as_str().ok_or_else(invalid_value)?)),
+            };
+        }
+        Property::NameProperty(property) => {
+            property.value = asset.add_fname(value.as_str().ok_or_else(invalid_value)?);
+        }
+        Property::EnumProperty(property) => {
+            property.value = Some(asset.add_fname(value.as_str().ok_or_else(invalid_value)?));
+        }
+        Property::ObjectProperty(property) => {
+            property.value = match value {
+                serde_json::Value::Null => PackageIndex::new(0),
+                _ => {
+                    let reference = ObjectReference::from_json(value).ok_or_else(invalid_value)?;
+                    match (find_object_import(asset, &reference), add_imports) {
+                        (Some(import), _) => import,
+                        (None, true) => add_object_import(asset, &reference),
+                        (None, false) => return Ok(None),
+                    }
+                }
+            };
+        }
+        Property::ArrayProperty(property) => {
+            let values = value.as_array().ok_or_else(invalid_value)?;
+            let element_type = property_type.and_then(|e| e.element());
+            let element = array_element_template(asset, property, element_type)?;
+
+            let mut elements = Vec::with_capacity(values.len());
+            for value in values {
+                let Some(element) =
+                    convert_property(asset, &element, value, element_type, add_imports)?
+                else {
+                    return Ok(None);
+                };
+                elements.push(element);
+            }
+            property.value = elements;
+        }
+        Property::StructProperty(property) => {
+            let fields = value.as_object().ok_or_else(invalid_value)?;
+            for (field_name, field_value) in fields {
+                let field_type = property_type.and_then(|e| e.field(field_name));
+                let field_index = match property
+                    .value
+                    .iter()
+                    .position(|e| e.get_name().get_content(|e| e == field_name.as_str()))
+                {
+                    Some(field_index) => field_index,
+                    None => {
+                        let field_type = field_type.ok_or_else(|| {
+                            io::Error::new(
+                                ErrorKind::Other,
+                                format!(
+                                    "Failed to find field {} in {}, give its type to add it",
+                                    field_name,
+                                    property.name.get_owned_content()
+                                ),
+                            )
+                        })?;
+                        property
+                            .value
+                            .push(default_property(asset, field_name, field_type)?);
+                        property.value.len() - 1
+                    }
+                };
+
+                let field = &mut property.value[field_index];
+                let Some(new_field) =
+                    convert_property(asset, field, field_value, field_type, add_imports)?
+                else {
+                    return Ok(None);
+                };
+                *field = new_field;
+            }
+        }
+        _ => {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "Unsupported property type for {}",
+                    template.get_name().get_owned_content()
+                ),
+            ))
+        }
+    }
+
+    Ok(Some(property))
+}
+
+/// Set a property of an export from a JSON value
+///
+/// If the property is not serialized it is added from `property_type`, as
+/// long as the struct containing it exists.
+pub(crate) fn set_export_property<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    export_index: usize,
+    path: &str,
+    value: &serde_json::Value,
+    property_type: Option<&PropertyType>,
+) -> Result<(), io::Error> {
+    let segments = parse_property_path(path)?;
+    let property_not_found =
+        || io::Error::new(ErrorKind::Other, format!("Failed to find property {path}"));
+
+    let exists = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .is_some();
+    if !exists {
+        let property_type = property_type.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                format!("Failed to find property {path}, give its type to add it"),
+            )
+        })?;
+        add_export_property(asset, export_index, &segments, property_type)
+            .map_err(|e| io::Error::new(ErrorKind::Other, format!("{path}: {e}")))?;
+    }
+
+    let template = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .ok_or_else(property_not_found)?
+        .clone();
+
+    let property = typed_property_from_json(asset, &template, value, property_type)?;
+
+    let target = asset.asset_data.exports[export_index]
+        .get_normal_export_mut()
+        .and_then(|e| find_property_mut(&mut e.properties, &segments))
+        .ok_or_else(property_not_found)?;
+    *target = property;
+
+    Ok(())
+}
+
+/// Add a default valued property at `path` to an export
+///
+/// The property is added to the export itself or to the struct containing it,
+/// array elements can't be added this way.
+pub(crate) fn add_export_property<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    export_index: usize,
+    path: &[PathSegment],
+    property_type: &PropertyType,
+) -> Result<(), io::Error> {
+    let Some((PathSegment::Name(name), parent_path)) = path.split_last() else {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            "Only named properties can be added",
+        ));
+    };
+
+    let property = default_property(asset, name, property_type)?;
+
+    let export = asset.asset_data.exports[export_index]
+        .get_normal_export_mut()
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid export"))?;
+    let properties = match parent_path.is_empty() {
+        true => &mut export.properties,
+        false => {
+            &mut find_property_mut(&mut export.properties, parent_path)
+                .and_then(|e| cast!(Property, StructProperty, e))
+                .ok_or_else(|| {
+                    io::Error::new(ErrorKind::Other, "Failed to find the containing struct")
+                })?
+                .value
+        }
+    };
+    properties.push(property);
+
+    Ok(())
+}
+
+/// Create a default valued property from its type
+pub(crate) fn default_property<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    name: &str,
+    property_type: &PropertyType,
+) -> Result<Property, io::Error> {
+    asset.add_fname(property_type.type_name());
+    let name = asset.add_fname(name);
+    let property: Property = match property_type {
+        PropertyType::Simple(type_name) => match type_name.as_str() {
+            "BoolProperty" => BoolProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: false,
+            }
+            .into(),
+            "IntProperty" => IntProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: 0,
+            }
+            .into(),
+            "Int64Property" => Int64Property {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: 0,
+            }
+            .into(),
+            "FloatProperty" => FloatProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: 0f32.into(),
+            }
+            .into(),
+            "DoubleProperty" => DoubleProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: 0f64.into(),
+            }
+            .into(),
+            "StrProperty" => StrProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: None,
+            }
+            .into(),
+            "NameProperty" => NameProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: asset.add_fname("None"),
+            }
+            .into(),
+            "ObjectProperty" => ObjectProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                value: PackageIndex::new(0),
+            }
+            .into(),
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Unsupported property type {type_name}"),
+                ))
+            }
+        },
+        PropertyType::Array { array } => ArrayProperty {
+            name,
+            ancestry: Ancestry::default(),
+            property_guid: Some(Guid::default()),
+            duplication_index: 0,
+            array_type: Some(asset.add_fname(array.type_name())),
+            value: Vec::new(),
+        }
+        .into(),
+        PropertyType::Map { map, value } => MapProperty {
+            name,
+            ancestry: Ancestry::default(),
+            property_guid: Some(Guid::default()),
+            duplication_index: 0,
+            key_type: asset.add_fname(map.type_name()),
+            value_type: asset.add_fname(value.type_name()),
+            value: IndexedMap::new(),
+            keys_to_remove: None,
+        }
+        .into(),
+        PropertyType::Set { set } => {
+            let array_type = asset.add_fname(set.type_name());
+            let elements = ArrayProperty {
+                name: name.clone(),
+                ancestry: Ancestry::default(),
+                property_guid: None,
+                duplication_index: 0,
+                array_type: Some(array_type.clone()),
+                value: Vec::new(),
+            };
+
+            SetProperty {
+                name,
+                ancestry: Ancestry::default(),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                array_type: Some(array_type),
+                value: elements.clone(),
+                removed_items: elements,
+            }
+            .into()
+        }
+        PropertyType::Struct {
+            struct_type,
+            fields,
+        } => {
+            let struct_type = struct_type.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "Missing struct_type for struct {}",
+                        name.get_owned_content()
+                    ),
+                )
+            })?;
+            let value = fields
+                .iter()
+                .map(|(field_name, field_type)| default_property(asset, field_name, field_type))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            StructProperty {
+                name,
+                ancestry: Ancestry::default(),
+                struct_type: Some(asset.add_fname(struct_type)),
+                struct_guid: Some(Guid::default()),
+                property_guid: Some(Guid::default()),
+                duplication_index: 0,
+                serialize_none: true,
+                value,
+            }
+            .into()
+        }
+    };
+
+    Ok(property)
+}
+
+/// Copy a property read from another asset into `asset`
+///
+/// Names and object references are only indices into the tables of the asset
+/// a property was read from, so they are added to `asset` again.
+pub(crate) fn copy_property<C: Read + Seek, D: Read + Seek>(
+    asset: &mut Asset<C>,
+    source: &Asset<D>,
+    property: &Property,
+) -> Result<Property, io::Error> {
+    macro_rules! copy_name {
+        ($property:expr) => {{
+            $property.name = asset.add_fname(&$property.name.get_owned_content());
+            $property.ancestry = Ancestry::default();
+        }};
+    }
+
+    let mut property = property.clone();
+    match &mut property {
+        Property::BoolProperty(property) => copy_name!(property),
+        Property::Int8Property(property) => copy_name!(property),
+        Property::Int16Property(property) => copy_name!(property),
+        Property::IntProperty(property) => copy_name!(property),
+        Property::Int64Property(property) => copy_name!(property),
+        Property::UInt16Property(property) => copy_name!(property),
+        Property::UInt32Property(property) => copy_name!(property),
+        Property::UInt64Property(property) => copy_name!(property),
+        Property::FloatProperty(property) => copy_name!(property),
+        Property::DoubleProperty(property) => copy_name!(property),
+        Property::StrProperty(property) => copy_name!(property),
+        Property::NameProperty(property) => {
+            copy_name!(property);
+            property.value = asset.add_fname(&property.value.get_owned_content());
+        }
+        Property::EnumProperty(property) => {
+            copy_name!(property);
+            for name in [
+                &mut property.enum_type,
+                &mut property.inner_type,
+                &mut property.value,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *name = asset.add_fname(&name.get_owned_content());
+            }
+        }
+        Property::ObjectProperty(property) => {
+            copy_name!(property);
+            property.value = copy_object_reference(asset, source, property.value)?;
+        }
+        Property::ArrayProperty(property) => {
+            copy_name!(property);
+            if let Some(array_type) = &mut property.array_type {
+                *array_type = asset.add_fname(&array_type.get_owned_content());
+            }
+            property.value = property
+                .value
+                .iter()
+                .map(|e| copy_property(asset, source, e))
+                .collect::<Result<_, _>>()?;
+        }
+        Property::StructProperty(property) => {
+            copy_name!(property);
+            if let Some(struct_type) = &mut property.struct_type {
+                *struct_type = asset.add_fname(&struct_type.get_owned_content());
+            }
+            property.value = property
+                .value
+                .iter()
+                .map(|e| copy_property(asset, source, e))
+                .collect::<Result<_, _>>()?;
+        }
+        property => {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "Cannot copy property {}, give its type",
+                    property.get_name().get_owned_content()
+                ),
+            ))
+        }
+    }
+
+    Ok(property)
+}
+
+/// Add the imports of an object referenced in another asset to `asset`
+fn copy_object_reference<C: Read + Seek, D: Read + Seek>(
+    asset: &mut Asset<C>,
+    source: &Asset<D>,
+    index: PackageIndex,
+) -> Result<PackageIndex, io::Error> {
+    if index.index == 0 {
+        return Ok(index);
+    }
+
+    let (import, package) = source
+        .get_import(index)
+        .and_then(|import| Some((import, source.get_import(import.outer_index)?)))
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::Other,
+                "Only references to imported objects can be copied",
+            )
+        })?;
+    let package_name = package.object_name.get_owned_content();
+    let class_package = import.class_package.get_owned_content();
+    let class_name = import.class_name.get_owned_content();
+    let reference = ObjectReference {
+        package_name: &package_name,
+        object_name: import.object_name.get_owned_content(),
+        class_package: &class_package,
+        class_name: &class_name,
+    };
+
+    Ok(match find_object_import(asset, &reference) {
+        Some(import) => import,
+        None => add_object_import(asset, &reference),
+    })
+}
+
+/// Get a property to create new elements of an array from
+///
+/// This is the first element of the array, or a default valued element of
+/// `element_type` or of the array's own element type for empty arrays.
+pub(crate) fn array_element_template<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    array: &ArrayProperty,
+    element_type: Option<&PropertyType>,
+) -> Result<Property, io::Error> {
+    element_template(
+        asset,
+        &array.name.get_owned_content(),
+        array.value.first(),
+        element_type,
+        array.array_type.as_ref().map(|e| e.get_owned_content()),
+    )
+}
+
+/// Get a property to create new elements of an array, map or set from
+///
+/// This is `existing` if the container has any elements, otherwise a default
+/// valued element of `element_type` or of the serialized `container_type`.
+/// Struct elements always need `element_type` as the serialized type doesn't
+/// name the struct.
+pub(crate) fn element_template<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    name: &str,
+    existing: Option<&Property>,
+    element_type: Option<&PropertyType>,
+    container_type: Option<String>,
+) -> Result<Property, io::Error> {
+    if let Some(element) = existing {
+        return Ok(element.clone());
+    }
+
+    let container_type = container_type.filter(|e| e != "StructProperty");
+    match (element_type, container_type) {
+        (Some(element_type), _) => default_property(asset, name, element_type),
+        (None, Some(container_type)) => {
+            default_property(asset, name, &PropertyType::Simple(container_type))
+        }
+        (None, None) => Err(io::Error::new(
+            ErrorKind::Other,
+            format!("Cannot infer element type of empty property {name}, give its type"),
+        )),
+    }
+}
+
+/// Find the index of the class default object export of a blueprint
+pub(crate) fn find_cdo<C: Read + Seek>(asset: &Asset<C>, name: &str) -> Result<usize, io::Error> {
+    asset
+        .asset_data
+        .exports
+        .iter()
+        .position(|e| {
+            e.get_normal_export()
+                .map(|e| {
+                    e.base_export
+                        .object_flags
+                        .contains(EObjectFlags::RF_CLASS_DEFAULT_OBJECT)
+                })
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, format!("Failed to find CDO in {name}")))
+}
+
+/// Convert a JSON number to an integer type, if it fits
+fn json_to_int<T: TryFrom<i64> + TryFrom<u64>>(value: &serde_json::Value) -> Option<T> {
+    match value.as_i64() {
+        Some(value) => <T as TryFrom<i64>>::try_from(value).ok(),
+        None => <T as TryFrom<u64>>::try_from(value.as_u64()?).ok(),
+    }
+}
+
+/// A reference to an object in another package
+///
+/// References are either a blueprint path, e.g. `"/Game/Items/Item"`, or an
+/// object with an explicit `class_package` and `class_name` for other asset
+/// types.
+struct ObjectReference<'a> {
+    package_name: &'a str,
+    object_name: String,
+    class_package: &'a str,
+    class_name: &'a str,
+}
+
+impl<'a> ObjectReference<'a> {
+    fn from_json(value: &'a serde_json::Value) -> Option<Self> {
+        let (path, class_package, class_name) = match value {
+            serde_json::Value::String(path) => (path.as_str(), "/Script/Engine", None),
+            serde_json::Value::Object(object) => (
+                object.get("path")?.as_str()?,
+                object.get("class_package")?.as_str()?,
+                Some(object.get("class_name")?.as_str()?),
+            ),
+            _ => return None,
+        };
+
+        let (package_name, object_name) = match path.split_once('.') {
+            Some((package_name, object_name)) => (package_name, String::from(object_name)),
+            None => {
+                let object_name = Path::new(path).file_stem()?.to_str()?;
+                match class_name {
+                    Some(_) => (path, String::from(object_name)),
+                    None => (path, String::from(object_name) + "_C"),
+                }
+            }
+        };
+
+        Some(ObjectReference {
+            package_name,
+            object_name,
+            class_package,
+            class_name: class_name.unwrap_or("BlueprintGeneratedClass"),
+        })
+    }
+}
+
+/// Find an existing import of a referenced object by its package and object name
+fn find_object_import<C: Read + Seek>(
+    asset: &Asset<C>,
+    reference: &ObjectReference,
+) -> Option<PackageIndex> {
+    asset
+        .imports
+        .iter()
+        .position(|import| {
+            import
+                .object_name
+                .get_content(|e| e == reference.object_name)
+                && asset
+                    .get_import(import.outer_index)
+                    .map(|package| {
+                        package
+                            .object_name
+                            .get_content(|e| e == reference.package_name)
+                    })
+                    .unwrap_or(false)
+        })
+        .map(|i| PackageIndex::new(-(i as i32) - 1))
+}
+
+/// Add imports for a referenced object
+fn add_object_import<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    reference: &ObjectReference,
+) -> PackageIndex {
+    let package_import = match asset.imports.iter().position(|import| {
+        import.outer_index.index == 0
+            && import
+                .object_name
+                .get_content(|e| e == reference.package_name)
+    }) {
+        Some(i) => PackageIndex::new(-(i as i32) - 1),
+        None => {
+            let package_import = Import {
+                class_package: asset.add_fname("/Script/CoreUObject"),
+                class_name: asset.add_fname("Package"),
+                outer_index: PackageIndex::new(0),
+                object_name: asset.add_fname(reference.package_name),
+                optional: false,
+            };
+            asset.add_import(package_import)
+        }
+    };
+
+    let import = Import {
+        class_package: asset.add_fname(reference.class_package),
+        class_name: asset.add_fname(reference.class_name),
+        outer_index: package_import,
+        object_name: asset.add_fname(&reference.object_name),
+        optional: false,
+    };
+    asset.add_import(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use unreal_mod_manager::unreal_asset::engine_version::EngineVersion;
+
+    use super::*;
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+
+    fn test_asset() -> Asset<Cursor<Vec<u8>>> {
+        Asset::new(
+            Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+            Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+            EngineVersion::VER_UE4_27,
+            None,
+        )
+        .expect("Corrupted ActorTemplate")
+    }
+
+    fn int_property<C: Read + Seek>(asset: &mut Asset<C>, name: &str, value: i32) -> Property {
+        IntProperty {
+            name: asset.add_fname(name),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            value,
+        }
+        .into()
+    }
+
+    fn struct_property<C: Read + Seek>(asset: &mut Asset<C>, value: Vec<Property>) -> Property {
+        StructProperty {
+            name: asset.add_fname("Test"),
+            ancestry: Ancestry::default(),
+            struct_type: Some(asset.add_fname("TestStruct")),
+            struct_guid: None,
+            property_guid: None,
+            duplication_index: 0,
+            serialize_none: true,
+            value,
+        }
+        .into()
+    }
+
+    fn object_property<C: Read + Seek>(asset: &mut Asset<C>) -> Property {
+        ObjectProperty {
+            name: asset.add_fname("Test"),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            value: PackageIndex::new(0),
+        }
+        .into()
+    }
+
+    fn int_field(property: &Property, name: &str) -> i32 {
+        cast!(Property, StructProperty, property)
+            .expect("Not a struct")
+            .value
+            .iter()
+            .find(|e| e.get_name().get_content(|e| e == name))
+            .and_then(|e| cast!(Property, IntProperty, e))
+            .expect("Missing field")
+            .value
+    }
+
+    #[test]
+    fn parse_nested_property_path() {
+        let segments = parse_property_path("SurfaceBiomes[0].Layers[2][1].Material").unwrap();
+
+        assert_eq!(
+            segments,
+            [
+                PathSegment::Name(String::from("SurfaceBiomes")),
+                PathSegment::Index(0),
+                PathSegment::Name(String::from("Layers")),
+                PathSegment::Index(2),
+                PathSegment::Index(1),
+                PathSegment::Name(String::from("Material")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_property_paths() {
+        for path in ["", "A.", ".A", "[0]", "A[", "A[x]", "A[0]x", "A[0]]"] {
+            assert!(parse_property_path(path).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn struct_from_json_keeps_other_fields() {
+        let mut asset = test_asset();
+        let fields = Vec::from([
+            int_property(&mut asset, "X", 1),
+            int_property(&mut asset, "Y", 2),
+        ]);
+        let template = struct_property(&mut asset, fields);
+
+        let property =
+            property_from_json(&mut asset, &template, &serde_json::json!({ "X": 5 })).unwrap();
+
+        assert_eq!(int_field(&property, "X"), 5);
+        assert_eq!(int_field(&property, "Y"), 2);
+    }
+
+    #[test]
+    fn struct_from_json_adds_typed_fields() {
+        let mut asset = test_asset();
+        let fields = Vec::from([int_property(&mut asset, "X", 1)]);
+        let template = struct_property(&mut asset, fields);
+        let value = serde_json::json!({ "Z": 3 });
+
+        assert!(property_from_json(&mut asset, &template, &value).is_err());
+
+        let property_type: PropertyType =
+            serde_json::from_value(serde_json::json!({ "fields": { "Z": "IntProperty" } }))
+                .unwrap();
+        let property =
+            typed_property_from_json(&mut asset, &template, &value, Some(&property_type)).unwrap();
+
+        assert_eq!(int_field(&property, "X"), 1);
+        assert_eq!(int_field(&property, "Z"), 3);
+    }
+
+    #[test]
+    fn empty_array_from_json() {
+        let mut asset = test_asset();
+        let template: Property = ArrayProperty {
+            name: asset.add_fname("Test"),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            array_type: Some(asset.add_fname("IntProperty")),
+            value: Vec::new(),
+        }
+        .into();
+
+        let property =
+            property_from_json(&mut asset, &template, &serde_json::json!([1, 2])).unwrap();
+
+        let values = cast!(Property, ArrayProperty, &property)
+            .expect("Not an array")
+            .value
+            .iter()
+            .map(|e| cast!(Property, IntProperty, e).expect("Not an int").value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1, 2]);
+    }
+
+    #[test]
+    fn invalid_json_value() {
+        let mut asset = test_asset();
+        let template = int_property(&mut asset, "Test", 1);
+
+        for value in [serde_json::json!("1"), serde_json::json!(1.5)] {
+            assert!(property_from_json(&mut asset, &template, &value).is_err());
+        }
+    }
+
+    #[test]
+    fn find_object_reference_without_adding_imports() {
+        let mut asset = test_asset();
+        let template = object_property(&mut asset);
+        let value = serde_json::json!("/Game/Test/TestActor");
+        let import_count = asset.imports.len();
+
+        assert!(find_property_from_json(&mut asset, &template, &value)
+            .unwrap()
+            .is_none());
+        assert_eq!(asset.imports.len(), import_count);
+
+        let added = property_from_json(&mut asset, &template, &value).unwrap();
+        assert_eq!(asset.imports.len(), import_count + 2);
+
+        let found = find_property_from_json(&mut asset, &template, &value).unwrap();
+        assert_eq!(found, Some(added));
+        assert_eq!(asset.imports.len(), import_count + 2);
+    }
+
+    #[test]
+    fn copy_object_property_between_assets() {
+        let mut source = test_asset();
+        let template = object_property(&mut source);
+        let value = serde_json::json!("/Game/Test/TestActor");
+        let property = property_from_json(&mut source, &template, &value).unwrap();
+
+        let mut asset = test_asset();
+        let import_count = asset.imports.len();
+        let copied = copy_property(&mut asset, &source, &property).unwrap();
+
+        assert_eq!(asset.imports.len(), import_count + 2);
+        let template = object_property(&mut asset);
+        let found = find_property_from_json(&mut asset, &template, &value).unwrap();
+        assert_eq!(found, Some(copied));
+    }
+}
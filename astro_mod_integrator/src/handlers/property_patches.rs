@@ -0,0 +1,469 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Seek};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::{ExportBaseTrait, ExportNormalTrait},
+    properties::Property,
+    Asset,
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::fname_matches;
+use super::properties::{
+    array_element_template, find_property, find_property_from_json, find_property_mut,
+    parse_property_path, set_export_property, typed_property_from_json, PathSegment, PropertyType,
+};
+use crate::AstroIntegratorConfig;
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum PatchOperation {
+    /// Set the property at `path` to `value`
+    #[default]
+    Set,
+    /// Append `value` to the array at `path`
+    Append,
+    /// Insert `value` into the array at `path` before `index`
+    Insert,
+    /// Remove the array element at `path`, e.g. `Layers[2]`, or every
+    /// element of the array at `path` equal to `value`
+    Remove,
+    /// Replace every element of the array at `path` equal to `from` with `value`
+    Replace,
+}
+
+/// A single edit to a property of an export
+///
+/// Array elements are created from the first element of the array, so
+/// struct values only need to contain the fields that differ from it.
+/// `property_type` is the type of the property at `path`, it is needed to
+/// add properties that are not serialized and elements to empty struct arrays.
+#[derive(Deserialize, Serialize, Debug)]
+struct PropertyPatch {
+    pub export: String,
+    pub path: String,
+    #[serde(default)]
+    pub operation: PatchOperation,
+    #[serde(default)]
+    pub value: Option<serde_json::Value>,
+    #[serde(default)]
+    pub index: Option<usize>,
+    #[serde(default)]
+    pub from: Option<serde_json::Value>,
+    #[serde(default)]
+    pub property_type: Option<PropertyType>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_property_patches(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    property_patches_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut asset_patches: HashMap<String, Vec<PropertyPatch>> = HashMap::new();
+
+    for property_patches_map in property_patches_maps {
+        let property_patches_map: HashMap<String, Vec<PropertyPatch>> =
+            serde_json::from_value(property_patches_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (name, patches) in property_patches_map {
+            asset_patches.entry(name).or_default().extend(patches);
+        }
+    }
+
+    for (name, patches) in &asset_patches {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for patch in patches {
+            let Some(export_index) = asset.asset_data.exports.iter().position(|e| {
+                e.get_normal_export().is_some()
+                    && fname_matches(&e.get_base_export().object_name, &patch.export)
+            }) else {
+                warn!("Failed to find export {} in {}", patch.export, name);
+                continue;
+            };
+
+            apply_patch(&mut asset, export_index, patch).map_err(|e| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!("{name}: {}.{}: {e}", patch.export, patch.path),
+                )
+            })?;
+        }
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn apply_patch<C: Read + Seek>(
+    asset: &mut Asset<C>,
+    export_index: usize,
+    patch: &PropertyPatch,
+) -> Result<(), io::Error> {
+    let missing_field =
+        |field: &str| io::Error::new(ErrorKind::Other, format!("Missing {field} for patch"));
+
+    let mut segments = parse_property_path(&patch.path)?;
+    let element_index = match (patch.operation, segments.last()) {
+        (PatchOperation::Remove, Some(PathSegment::Index(index))) if patch.value.is_none() => {
+            let index = *index;
+            segments.pop();
+            Some(index)
+        }
+        _ => None,
+    };
+
+    if patch.operation == PatchOperation::Set {
+        let value = patch.value.as_ref().ok_or_else(|| missing_field("value"))?;
+        return set_export_property(
+            asset,
+            export_index,
+            &patch.path,
+            value,
+            patch.property_type.as_ref(),
+        );
+    }
+
+    let array_exists = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .is_some();
+    if !array_exists && element_index.is_none() && patch.property_type.is_some() {
+        set_export_property(
+            asset,
+            export_index,
+            &patch.path,
+            &serde_json::Value::Array(Vec::new()),
+            patch.property_type.as_ref(),
+        )?;
+    }
+
+    let element_type = patch.property_type.as_ref().and_then(|e| e.element());
+    let array = asset.asset_data.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| find_property(&e.properties, &segments))
+        .and_then(|e| cast!(Property, ArrayProperty, e))
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Failed to find array property"))?
+        .clone();
+
+    // nothing can match in an empty array, its element type isn't needed then
+    if array.value.is_empty()
+        && element_index.is_none()
+        && matches!(
+            patch.operation,
+            PatchOperation::Remove | PatchOperation::Replace
+        )
+    {
+        warn!("No elements in {} to remove or replace", patch.path);
+        return Ok(());
+    }
+
+    let template = match patch.operation {
+        PatchOperation::Remove if element_index.is_some() => None,
+        _ => Some(array_element_template(asset, &array, element_type)?),
+    };
+
+    // Elements to remove or replace are only matched against, so objects
+    // they reference are looked up in the existing imports instead of added
+    let from = match patch.operation {
+        PatchOperation::Replace => Some(patch.from.as_ref().ok_or_else(|| missing_field("from"))?),
+        PatchOperation::Remove => patch.value.as_ref(),
+        _ => None,
+    };
+    let from = match from {
+        Some(from) => {
+            let template = template.as_ref().expect("Checked above");
+            let Some(from) = find_property_from_json(asset, template, from)? else {
+                warn!("No elements matching {} to remove or replace", from);
+                return Ok(());
+            };
+            Some(from)
+        }
+        None => None,
+    };
+
+    let value = match (patch.operation, &patch.value) {
+        (PatchOperation::Remove, _) => from.clone(),
+        (_, Some(value)) => {
+            let template = template.as_ref().expect("Checked above");
+            Some(typed_property_from_json(
+                asset,
+                template,
+                value,
+                element_type,
+            )?)
+        }
+        (_, None) => return Err(missing_field("value")),
+    };
+
+    let array = asset.asset_data.exports[export_index]
+        .get_normal_export_mut()
+        .and_then(|e| find_property_mut(&mut e.properties, &segments))
+        .and_then(|e| cast!(Property, ArrayProperty, e))
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Failed to find array property"))?;
+
+    match (patch.operation, value) {
+        (PatchOperation::Append, Some(value)) => array.value.push(value),
+        (PatchOperation::Insert, Some(value)) => {
+            let index = patch.index.ok_or_else(|| missing_field("index"))?;
+            if index > array.value.len() {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Index {index} is out of bounds"),
+                ));
+            }
+            array.value.insert(index, value);
+        }
+        (PatchOperation::Remove, Some(value)) => {
+            let len = array.value.len();
+            array.value.retain(|e| e != &value);
+            if array.value.len() == len {
+                warn!("No elements matching {:?} to remove", patch.value);
+            }
+        }
+        (PatchOperation::Remove, None) => {
+            let index = element_index.ok_or_else(|| missing_field("value"))?;
+            if index >= array.value.len() {
+                return Err(io::Error::new(
+                    ErrorKind::Other,
+                    format!("Index {index} is out of bounds"),
+                ));
+            }
+            array.value.remove(index);
+        }
+        (PatchOperation::Replace, Some(value)) => {
+            let from = from.expect("Checked above");
+            let mut replaced = false;
+            for element in array.value.iter_mut().filter(|e| **e == from) {
+                *element = value.clone();
+                replaced = true;
+            }
+            if !replaced {
+                warn!("No elements matching {:?} to replace", patch.from);
+            }
+        }
+        (operation, _) => {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("Missing value for {operation:?} patch"),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use unreal_mod_manager::unreal_asset::{
+        properties::{array_property::ArrayProperty, int_property::IntProperty},
+        unversioned::ancestry::Ancestry,
+    };
+
+    use super::*;
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT};
+
+    fn test_asset(values: &[i32]) -> (Asset<Cursor<Vec<u8>>>, usize) {
+        let mut asset = Asset::new(
+            Cursor::new(ACTOR_TEMPLATE_ASSET.to_vec()),
+            Some(Cursor::new(ACTOR_TEMPLATE_EXPORT.to_vec())),
+            EngineVersion::VER_UE4_27,
+            None,
+        )
+        .expect("Corrupted ActorTemplate");
+        let export_index = asset
+            .asset_data
+            .exports
+            .iter()
+            .position(|e| e.get_normal_export().is_some())
+            .expect("Missing normal export");
+
+        let name = asset.add_fname("TestArray");
+        let array = ArrayProperty {
+            name: name.clone(),
+            ancestry: Ancestry::default(),
+            property_guid: None,
+            duplication_index: 0,
+            array_type: Some(asset.add_fname("IntProperty")),
+            value: values
+                .iter()
+                .map(|value| {
+                    IntProperty {
+                        name: name.clone(),
+                        ancestry: Ancestry::default(),
+                        property_guid: None,
+                        duplication_index: 0,
+                        value: *value,
+                    }
+                    .into()
+                })
+                .collect(),
+        };
+        asset.asset_data.exports[export_index]
+            .get_normal_export_mut()
+            .unwrap()
+            .properties
+            .push(array.into());
+
+        (asset, export_index)
+    }
+
+    fn values<C: Read + Seek>(asset: &Asset<C>, export_index: usize) -> Vec<i32> {
+        asset.asset_data.exports[export_index]
+            .get_normal_export()
+            .and_then(|e| find_property(&e.properties, &parse_property_path("TestArray").unwrap()))
+            .and_then(|e| cast!(Property, ArrayProperty, e))
+            .expect("Missing array")
+            .value
+            .iter()
+            .map(|e| cast!(Property, IntProperty, e).expect("Not an int").value)
+            .collect()
+    }
+
+    fn patch(value: serde_json::Value) -> PropertyPatch {
+        serde_json::from_value(value).expect("Invalid patch")
+    }
+
+    #[test]
+    fn append_and_insert() {
+        let (mut asset, export_index) = test_asset(&[1, 2]);
+
+        let append = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray",
+            "operation": "append",
+            "value": 3
+        }));
+        apply_patch(&mut asset, export_index, &append).unwrap();
+        let insert = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray",
+            "operation": "insert",
+            "index": 0,
+            "value": 0
+        }));
+        apply_patch(&mut asset, export_index, &insert).unwrap();
+
+        assert_eq!(values(&asset, export_index), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn insert_out_of_bounds() {
+        let (mut asset, export_index) = test_asset(&[1]);
+
+        let insert = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray",
+            "operation": "insert",
+            "index": 2,
+            "value": 0
+        }));
+        assert!(apply_patch(&mut asset, export_index, &insert).is_err());
+        assert_eq!(values(&asset, export_index), [1]);
+    }
+
+    #[test]
+    fn remove_by_index_and_value() {
+        let (mut asset, export_index) = test_asset(&[1, 2, 3, 2]);
+
+        let remove_index = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray[0]",
+            "operation": "remove"
+        }));
+        apply_patch(&mut asset, export_index, &remove_index).unwrap();
+        let remove_value = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray",
+            "operation": "remove",
+            "value": 2
+        }));
+        apply_patch(&mut asset, export_index, &remove_value).unwrap();
+
+        assert_eq!(values(&asset, export_index), [3]);
+    }
+
+    #[test]
+    fn replace_matching_elements() {
+        let (mut asset, export_index) = test_asset(&[1, 2, 1]);
+
+        let replace = patch(serde_json::json!({
+            "export": "Test",
+            "path": "TestArray",
+            "operation": "replace",
+            "from": 1,
+            "value": 5
+        }));
+        apply_patch(&mut asset, export_index, &replace).unwrap();
+
+        assert_eq!(values(&asset, export_index), [5, 2, 5]);
+    }
+
+    #[test]
+    fn remove_and_replace_skip_empty_arrays() {
+        let (mut asset, export_index) = test_asset(&[]);
+
+        for operation in ["remove", "replace"] {
+            let patch = patch(serde_json::json!({
+                "export": "Test",
+                "path": "TestArray",
+                "operation": operation,
+                "from": 1,
+                "value": 5
+            }));
+            apply_patch(&mut asset, export_index, &patch).unwrap();
+        }
+
+        assert!(values(&asset, export_index).is_empty());
+    }
+
+    #[test]
+    fn append_to_unserialized_array() {
+        let (mut asset, export_index) = test_asset(&[]);
+
+        let append = patch(serde_json::json!({
+            "export": "Test",
+            "path": "OtherArray",
+            "operation": "append",
+            "value": 1,
+            "property_type": { "array": "IntProperty" }
+        }));
+        apply_patch(&mut asset, export_index, &append).unwrap();
+
+        let array = asset.asset_data.exports[export_index]
+            .get_normal_export()
+            .and_then(|e| find_property(&e.properties, &parse_property_path("OtherArray").unwrap()))
+            .and_then(|e| cast!(Property, ArrayProperty, e))
+            .expect("Missing array");
+        assert_eq!(array.value.len(), 1);
+    }
+}
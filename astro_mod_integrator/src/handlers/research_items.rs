@@ -0,0 +1,127 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::engine_version::EngineVersion;
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::item_list_entries::add_item_list_entries;
+use super::properties::{find_cdo, set_export_property, PropertyType};
+use crate::AstroIntegratorConfig;
+
+const MASTER_ITEM_LIST: &str = "/Game/Items/ItemTypes/MasterItemList";
+const INITIAL_KNOWN_ITEM_LISTS: [&str; 2] = [
+    "/Game/Items/ItemTypes/BaseGameInitialKnownItemList",
+    "/Game/U32_Expansion/Items/GW_InitialKnownItemList",
+];
+/// Array holding the item types of every item list
+const ITEM_TYPES_ARRAY: &str = "ItemTypes";
+/// Path of the research cost in bytes on item type CDOs
+const BYTE_COST_PATH: &str = "CatalogData.UnlockCost";
+const BYTE_COST_TYPE: &str = "IntProperty";
+
+fn default_true() -> bool {
+    true
+}
+
+/// An item type to make researchable
+///
+/// `catalogs` are the item lists of the printers the item can be printed
+/// from, e.g. `/Game/Items/ItemLists/T2PrinterItemList`. Vanilla items can be
+/// given a new `byte_cost` by setting `register` to `false`.
+#[derive(Deserialize, Serialize, Debug)]
+struct ResearchItem {
+    pub item_type: String,
+    #[serde(default)]
+    pub byte_cost: Option<u32>,
+    #[serde(default)]
+    pub catalogs: Vec<String>,
+    #[serde(default)]
+    pub known_at_start: bool,
+    #[serde(default = "default_true")]
+    pub register: bool,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_research_items(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    research_items: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut items = Vec::new();
+
+    for mod_items in research_items {
+        let mod_items: Vec<ResearchItem> = serde_json::from_value(mod_items.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+        items.extend(mod_items);
+    }
+
+    let mut item_lists: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
+    let mut add_to_list = |list: &str, item_type: &str| {
+        item_lists
+            .entry(String::from(list))
+            .or_default()
+            .entry(String::from(ITEM_TYPES_ARRAY))
+            .or_default()
+            .push(String::from(item_type));
+    };
+
+    for item in &items {
+        if item.register {
+            add_to_list(MASTER_ITEM_LIST, &item.item_type);
+        }
+        if item.known_at_start {
+            for list in INITIAL_KNOWN_ITEM_LISTS {
+                add_to_list(list, &item.item_type);
+            }
+        }
+        for catalog in &item.catalogs {
+            add_to_list(catalog, &item.item_type);
+        }
+    }
+
+    add_item_list_entries(integrated_pak, game_paks, mod_paks, &item_lists)?;
+
+    for item in &items {
+        let Some(byte_cost) = item.byte_cost else {
+            continue;
+        };
+
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, &item.item_type)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let cdo_index = find_cdo(&asset, &item.item_type)?;
+
+        set_export_property(
+            &mut asset,
+            cdo_index,
+            BYTE_COST_PATH,
+            &serde_json::Value::from(byte_cost),
+            Some(&PropertyType::Simple(String::from(BYTE_COST_TYPE))),
+        )
+        .map_err(|e| io::Error::new(ErrorKind::Other, format!("{}: {e}", item.item_type)))?;
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::properties::object_property::TopLevelAssetPath;
+use unreal_mod_manager::unreal_asset::unversioned::ancestry::Ancestry;
+use unreal_mod_manager::unreal_asset::{
+    cast,
+    engine_version::EngineVersion,
+    exports::{ExportBaseTrait, ExportNormalTrait},
+    properties::{
+        object_property::{SoftObjectPath, SoftObjectProperty},
+        Property,
+    },
+};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::fname_matches;
+use super::properties::{find_property, find_property_mut, parse_property_path};
+use crate::AstroIntegratorConfig;
+
+/// A soft object reference, either just an asset path or with a sub path
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+enum SoftReference {
+    AssetPath(String),
+    Reference {
+        asset_path: String,
+        #[serde(default)]
+        sub_path_string: Option<String>,
+    },
+}
+
+impl SoftReference {
+    fn asset_path(&self) -> &str {
+        match self {
+            SoftReference::AssetPath(asset_path) => asset_path,
+            SoftReference::Reference { asset_path, .. } => asset_path,
+        }
+    }
+
+    fn sub_path_string(&self) -> Option<&str> {
+        match self {
+            SoftReference::AssetPath(_) => None,
+            SoftReference::Reference {
+                sub_path_string, ..
+            } => sub_path_string.as_deref(),
+        }
+    }
+}
+
+/// Soft references to add to an array
+///
+/// Without an `export` the references are added to the array in every export
+/// that has one at the given path. References already in the array are skipped.
+#[derive(Deserialize, Serialize, Debug)]
+struct SoftReferenceArray {
+    #[serde(default)]
+    pub export: Option<String>,
+    pub array: String,
+    pub entries: Vec<SoftReference>,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_soft_reference_arrays(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    soft_reference_arrays_maps: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut asset_arrays: HashMap<String, Vec<SoftReferenceArray>> = HashMap::new();
+
+    for soft_reference_arrays_map in soft_reference_arrays_maps {
+        let soft_reference_arrays_map: HashMap<String, Vec<SoftReferenceArray>> =
+            serde_json::from_value(soft_reference_arrays_map.clone())
+                .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for (name, arrays) in soft_reference_arrays_map {
+            asset_arrays.entry(name).or_default().extend(arrays);
+        }
+    }
+
+    for (name, arrays) in &asset_arrays {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        for array in arrays {
+            let segments = parse_property_path(&array.array)?;
+
+            let export_indices: Vec<usize> = asset
+                .asset_data
+                .exports
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| {
+                    array
+                        .export
+                        .as_ref()
+                        .map(|export| fname_matches(&e.get_base_export().object_name, export))
+                        .unwrap_or(true)
+                })
+                .filter_map(|(i, e)| {
+                    e.get_normal_export()
+                        .and_then(|e| find_property(&e.properties, &segments))
+                        .and_then(|e| cast!(Property, ArrayProperty, e))
+                        .and_then(|e| e.array_type.as_ref())
+                        .filter(|e| e.get_content(|e| e == "SoftObjectProperty"))
+                        .map(|_| i)
+                })
+                .collect();
+            if export_indices.is_empty() {
+                warn!(
+                    "Failed to find soft reference array {} in {}",
+                    array.array, name
+                );
+                continue;
+            }
+
+            let mut new_references = Vec::new();
+            for entry in &array.entries {
+                asset.add_name_reference(String::from(entry.asset_path()), false);
+                new_references.push((
+                    asset.add_fname(entry.asset_path()),
+                    entry.sub_path_string().map(String::from),
+                ));
+            }
+
+            for export_index in export_indices {
+                let array_property = asset.asset_data.exports[export_index]
+                    .get_normal_export_mut()
+                    .and_then(|e| find_property_mut(&mut e.properties, &segments))
+                    .and_then(|e| cast!(Property, ArrayProperty, e))
+                    .expect("Corrupted memory");
+
+                for (asset_path, sub_path_string) in &new_references {
+                    let exists = array_property.value.iter().any(|e| {
+                        cast!(Property, SoftObjectProperty, e)
+                            .map(|e| {
+                                e.value.asset_path.asset_name == *asset_path
+                                    && e.value.sub_path_string == *sub_path_string
+                            })
+                            .unwrap_or(false)
+                    });
+                    if exists {
+                        continue;
+                    }
+
+                    array_property.value.push(
+                        SoftObjectProperty {
+                            name: array_property.name.clone(),
+                            ancestry: Ancestry::default(),
+                            property_guid: None,
+                            duplication_index: 0,
+                            value: SoftObjectPath {
+                                asset_path: TopLevelAssetPath::new(None, asset_path.clone()),
+                                sub_path_string: sub_path_string.clone(),
+                            },
+                        }
+                        .into(),
+                    );
+                }
+            }
+        }
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use unreal_mod_manager::unreal_asset::{cast, engine_version::EngineVersion, exports::Export};
+use unreal_mod_manager::unreal_helpers::game_to_absolute;
+use unreal_mod_manager::unreal_mod_integrator::{
+    helpers::{get_asset, write_asset},
+    Error, IntegratorConfig,
+};
+use unreal_mod_manager::unreal_pak::{PakMemory, PakReader};
+
+use super::read_pak_file;
+use crate::locres::LocRes;
+use crate::AstroIntegratorConfig;
+
+/// Culture the game's StringTables are written in
+const NATIVE_CULTURE: &str = "en";
+
+/// A localized string keyed by culture
+///
+/// Entries with a `table` are added to that StringTable using the native
+/// culture value, or keep the table's existing value when only translations
+/// are given. Entries without a `table` only go into the localization
+/// resources and need a `namespace` along with the `source` string the game
+/// displays them with.
+#[derive(Deserialize, Serialize, Debug)]
+struct LocalizedEntry {
+    #[serde(default)]
+    pub table: Option<String>,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub key: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    pub values: BTreeMap<String, String>,
+}
+
+impl LocalizedEntry {
+    fn source(&self) -> Option<&String> {
+        self.source
+            .as_ref()
+            .or_else(|| self.values.get(NATIVE_CULTURE))
+    }
+}
+
+/// A string to add to a culture's localization resource
+struct Translation<'a> {
+    namespace: String,
+    key: &'a str,
+    source: String,
+    localized: &'a str,
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_string_table_entries(
+    _data: &(),
+    integrated_pak: &mut PakMemory,
+    game_paks: &mut Vec<PakReader<BufReader<File>>>,
+    mod_paks: &mut Vec<PakReader<BufReader<File>>>,
+    string_table_entries: &Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    let mut table_entries: HashMap<String, Vec<LocalizedEntry>> = HashMap::new();
+    let mut loose_entries = Vec::new();
+
+    for mod_entries in string_table_entries {
+        let mod_entries: Vec<LocalizedEntry> = serde_json::from_value(mod_entries.clone())
+            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+
+        for entry in mod_entries {
+            match &entry.table {
+                Some(table) => table_entries.entry(table.clone()).or_default().push(entry),
+                None => loose_entries.push(entry),
+            }
+        }
+    }
+
+    let mut translations: BTreeMap<&str, Vec<Translation>> = BTreeMap::new();
+
+    for (table_name, entries) in &table_entries {
+        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, table_name)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            EngineVersion::VER_UE4_27,
+        )?;
+
+        let string_table = asset
+            .asset_data
+            .exports
+            .iter_mut()
+            .find_map(|e| cast!(Export, StringTableExport, e))
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::Other,
+                    format!("Failed to find StringTable in {table_name}"),
+                )
+            })?;
+        let namespace = string_table.table.namespace.clone().unwrap_or_default();
+
+        for entry in entries {
+            let source = match entry.source() {
+                Some(source) => {
+                    string_table
+                        .table
+                        .value
+                        .insert(entry.key.clone(), source.clone());
+                    source.clone()
+                }
+                None => match string_table.table.value.get_by_key(&entry.key) {
+                    Some(source) => source.clone(),
+                    None => {
+                        warn!(
+                            "Missing {} value for new entry {} in {}",
+                            NATIVE_CULTURE, entry.key, table_name
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            for (culture, localized) in &entry.values {
+                translations.entry(culture).or_default().push(Translation {
+                    namespace: namespace.clone(),
+                    key: &entry.key,
+                    source: source.clone(),
+                    localized,
+                });
+            }
+        }
+
+        write_asset(integrated_pak, &asset, &asset_name)
+            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    }
+
+    for entry in &loose_entries {
+        let (Some(namespace), Some(source)) = (&entry.namespace, entry.source()) else {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "Entry {} needs a table, or a namespace and source string",
+                    entry.key
+                ),
+            )
+            .into());
+        };
+
+        for (culture, localized) in &entry.values {
+            translations.entry(culture).or_default().push(Translation {
+                namespace: namespace.clone(),
+                key: &entry.key,
+                source: source.clone(),
+                localized,
+            });
+        }
+    }
+
+    for (culture, translations) in &translations {
+        let locres_name = format!(
+            "{}/Content/Localization/Game/{}/Game.locres",
+            AstroIntegratorConfig::GAME_NAME,
+            culture
+        );
+
+        let Some(data) = read_pak_file(integrated_pak, game_paks, mod_paks, &locres_name)? else {
+            warn!("Game has no localization for culture {}, skipping", culture);
+            continue;
+        };
+
+        let mut locres = LocRes::read(&data)?;
+        for translation in translations {
+            locres.insert(
+                &translation.namespace,
+                translation.key,
+                &translation.source,
+                translation.localized,
+            );
+        }
+
+        integrated_pak.set_entry(locres_name, locres.write()?);
+    }
+
+    Ok(())
+}
@@ -5,15 +5,20 @@ use lazy_static::lazy_static;
 use unreal_mod_manager::unreal_asset::engine_version::EngineVersion;
 //use unreal_mod_manager::unreal_helpers::game_to_absolute;
 use unreal_mod_manager::unreal_mod_integrator::{
-    BakedMod, Error, HandlerFn, IntegratorConfig, IntegratorMod,
+    Error, HandlerFn, IntegratorConfig, IntegratorMod,
 };
 
 pub mod assets;
 pub(crate) mod baked;
 pub(crate) mod handlers;
+pub(crate) mod locres;
 
 use crate::handlers::{
-    biome_placement_modifiers, item_list_entries, linked_actor_components, mission_trailheads,
+    asset_redirects, biome_placement_modifiers, blueprint_parents, cdo_overrides,
+    configured_persistent_actors, crafting_recipes, data_table_rows, item_list_entries,
+    level_actors, linked_actor_components, map_set_entries, mission_trailheads,
+    planet_voxel_properties, property_patches, research_items, soft_reference_arrays,
+    string_table_entries,
 };
 
 pub struct AstroIntegratorConfig;
@@ -68,18 +73,76 @@ impl<'data> IntegratorConfig<'data, (), Error> for AstroIntegratorConfig {
             Box::new(biome_placement_modifiers::handle_biome_placement_modifiers),
         );
 
+        handlers.insert(
+            String::from("planet_voxel_properties"),
+            Box::new(planet_voxel_properties::handle_planet_voxel_properties),
+        );
+
+        handlers.insert(
+            String::from("data_table_rows"),
+            Box::new(data_table_rows::handle_data_table_rows),
+        );
+
+        handlers.insert(
+            String::from("cdo_overrides"),
+            Box::new(cdo_overrides::handle_cdo_overrides),
+        );
+
+        handlers.insert(
+            String::from("string_table_entries"),
+            Box::new(string_table_entries::handle_string_table_entries),
+        );
+
+        handlers.insert(
+            String::from("property_patches"),
+            Box::new(property_patches::handle_property_patches),
+        );
+
+        handlers.insert(
+            String::from("level_actors"),
+            Box::new(level_actors::handle_level_actors),
+        );
+
+        handlers.insert(
+            String::from("blueprint_parents"),
+            Box::new(blueprint_parents::handle_blueprint_parents),
+        );
+
+        handlers.insert(
+            String::from("asset_redirects"),
+            Box::new(asset_redirects::handle_asset_redirects),
+        );
+
+        handlers.insert(
+            String::from("research_items"),
+            Box::new(research_items::handle_research_items),
+        );
+
+        handlers.insert(
+            String::from("crafting_recipes"),
+            Box::new(crafting_recipes::handle_crafting_recipes),
+        );
+
+        handlers.insert(
+            String::from("soft_reference_arrays"),
+            Box::new(soft_reference_arrays::handle_soft_reference_arrays),
+        );
+
+        handlers.insert(
+            String::from("map_set_entries"),
+            Box::new(map_set_entries::handle_map_set_entries),
+        );
+
+        handlers.insert(
+            String::from("configured_persistent_actors"),
+            Box::new(configured_persistent_actors::handle_configured_persistent_actors),
+        );
+
         handlers
     }
 
     fn get_baked_mods(&self) -> Vec<IntegratorMod<Error>> {
-        Vec::from([BakedMod {
-            data: baked::CORE_MOD,
-            mod_id: "CoreMod".to_string(),
-            filename: "800-CoreMod-0.1.0_P.pak",
-            is_core: true,
-            priority: 800,
-        }
-        .into()])
+        baked::baked_mods().into_iter().map(|e| e.into()).collect()
     }
 
     const GAME_NAME: &'static str = "Astro";
@@ -0,0 +1,390 @@
+#![allow(clippy::io_other_error)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+
+const LOCRES_MAGIC: [u32; 4] = [0x7574140E, 0xFC034A67, 0x9D90154A, 0x1B7F37C3];
+
+/// Version written by [`LocRes::write`], the compact format doesn't need key hashes
+const LOCRES_VERSION_COMPACT: u8 = 1;
+const LOCRES_VERSION_OPTIMIZED_CRC32: u8 = 2;
+const LOCRES_VERSION_LATEST: u8 = 3;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB88320,
+                _ => crc >> 1,
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LocResEntry {
+    /// Hash of the source string this entry was translated from
+    pub source_string_hash: u32,
+    pub localized_string: String,
+}
+
+/// Localization resource, localized strings keyed by namespace and key
+#[derive(Debug, Default)]
+pub(crate) struct LocRes {
+    pub entries: BTreeMap<String, BTreeMap<String, LocResEntry>>,
+}
+
+impl LocRes {
+    /// Read a localization resource of any version up to 4.27
+    pub(crate) fn read(data: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = Cursor::new(data);
+
+        let mut magic = [0u32; 4];
+        for part in &mut magic {
+            *part = read_u32(&mut cursor)?;
+        }
+        let version = match magic == LOCRES_MAGIC {
+            true => read_u8(&mut cursor)?,
+            false => {
+                cursor.set_position(0);
+                0
+            }
+        };
+        if version > LOCRES_VERSION_LATEST {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("Unsupported locres version {version}"),
+            ));
+        }
+
+        let mut strings = Vec::new();
+        if version >= LOCRES_VERSION_COMPACT {
+            let strings_offset = read_i64(&mut cursor)?;
+            let position = cursor.position();
+
+            cursor.set_position(strings_offset as u64);
+            let string_count = read_i32(&mut cursor)?;
+            for _ in 0..string_count {
+                strings.push(read_fstring(&mut cursor)?);
+                if version >= LOCRES_VERSION_OPTIMIZED_CRC32 {
+                    // reference count
+                    read_i32(&mut cursor)?;
+                }
+            }
+
+            cursor.set_position(position);
+        }
+
+        if version >= LOCRES_VERSION_OPTIMIZED_CRC32 {
+            // entry count
+            read_u32(&mut cursor)?;
+        }
+
+        let mut entries = BTreeMap::new();
+        let namespace_count = read_u32(&mut cursor)?;
+        for _ in 0..namespace_count {
+            if version >= LOCRES_VERSION_OPTIMIZED_CRC32 {
+                // namespace hash
+                read_u32(&mut cursor)?;
+            }
+            let namespace = read_fstring(&mut cursor)?;
+            let keys: &mut BTreeMap<String, LocResEntry> = entries.entry(namespace).or_default();
+
+            let key_count = read_u32(&mut cursor)?;
+            for _ in 0..key_count {
+                if version >= LOCRES_VERSION_OPTIMIZED_CRC32 {
+                    // key hash
+                    read_u32(&mut cursor)?;
+                }
+                let key = read_fstring(&mut cursor)?;
+                let source_string_hash = read_u32(&mut cursor)?;
+                let localized_string = match version >= LOCRES_VERSION_COMPACT {
+                    true => {
+                        let index = read_i32(&mut cursor)?;
+                        strings.get(index as usize).cloned().ok_or_else(|| {
+                            io::Error::new(ErrorKind::Other, "Invalid locres string index")
+                        })?
+                    }
+                    false => read_fstring(&mut cursor)?,
+                };
+
+                keys.insert(
+                    key,
+                    LocResEntry {
+                        source_string_hash,
+                        localized_string,
+                    },
+                );
+            }
+        }
+
+        Ok(LocRes { entries })
+    }
+
+    /// Write the localization resource in the compact format
+    pub(crate) fn write(&self) -> Result<Vec<u8>, io::Error> {
+        let mut cursor = Cursor::new(Vec::new());
+
+        for part in LOCRES_MAGIC {
+            cursor.write_all(&part.to_le_bytes())?;
+        }
+        cursor.write_all(&[LOCRES_VERSION_COMPACT])?;
+
+        let strings_offset_position = cursor.position();
+        cursor.write_all(&0i64.to_le_bytes())?;
+
+        let mut strings: Vec<&str> = Vec::new();
+        let mut string_indices: HashMap<&str, i32> = HashMap::new();
+
+        cursor.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (namespace, keys) in &self.entries {
+            write_fstring(&mut cursor, namespace)?;
+            cursor.write_all(&(keys.len() as u32).to_le_bytes())?;
+
+            for (key, entry) in keys {
+                write_fstring(&mut cursor, key)?;
+                cursor.write_all(&entry.source_string_hash.to_le_bytes())?;
+
+                let index = *string_indices
+                    .entry(&entry.localized_string)
+                    .or_insert_with(|| {
+                        strings.push(&entry.localized_string);
+                        strings.len() as i32 - 1
+                    });
+                cursor.write_all(&index.to_le_bytes())?;
+            }
+        }
+
+        let strings_offset = cursor.position();
+        cursor.write_all(&(strings.len() as i32).to_le_bytes())?;
+        for string in strings {
+            write_fstring(&mut cursor, string)?;
+        }
+
+        cursor.seek(SeekFrom::Start(strings_offset_position))?;
+        cursor.write_all(&(strings_offset as i64).to_le_bytes())?;
+
+        Ok(cursor.into_inner())
+    }
+
+    /// Add or replace a localized string
+    pub(crate) fn insert(&mut self, namespace: &str, key: &str, source: &str, localized: &str) {
+        self.entries
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(
+                key.to_string(),
+                LocResEntry {
+                    source_string_hash: str_crc32(source),
+                    localized_string: localized.to_string(),
+                },
+            );
+    }
+}
+
+/// Hash a string the same way as the engine's `FCrc::StrCrc32`
+pub(crate) fn str_crc32(value: &str) -> u32 {
+    let mut crc = !0u32;
+    for unit in value.encode_utf16() {
+        for byte in (unit as u32).to_le_bytes() {
+            crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize];
+        }
+    }
+    !crc
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, io::Error> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, io::Error> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> Result<i32, io::Error> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, io::Error> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+fn read_fstring<R: Read>(reader: &mut R) -> Result<String, io::Error> {
+    let len = read_i32(reader)?;
+    match len {
+        0 => Ok(String::new()),
+        // latin-1 with a null terminator
+        1.. => {
+            let mut buf = vec![0u8; len as usize];
+            reader.read_exact(&mut buf)?;
+            buf.pop();
+            Ok(buf.into_iter().map(char::from).collect())
+        }
+        // utf-16 with a null terminator
+        _ => {
+            let mut buf = vec![0u8; len.unsigned_abs() as usize * 2];
+            reader.read_exact(&mut buf)?;
+            let mut units: Vec<u16> = buf
+                .chunks_exact(2)
+                .map(|e| u16::from_le_bytes([e[0], e[1]]))
+                .collect();
+            units.pop();
+            String::from_utf16(&units).map_err(|e| io::Error::new(ErrorKind::Other, e))
+        }
+    }
+}
+
+fn write_fstring<W: Write>(writer: &mut W, value: &str) -> Result<(), io::Error> {
+    if value.is_empty() {
+        return writer.write_all(&0i32.to_le_bytes());
+    }
+
+    if value.is_ascii() {
+        writer.write_all(&(value.len() as i32 + 1).to_le_bytes())?;
+        writer.write_all(value.as_bytes())?;
+        writer.write_all(&[0])
+    } else {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        writer.write_all(&(-(units.len() as i32 + 1)).to_le_bytes())?;
+        for unit in units {
+            writer.write_all(&unit.to_le_bytes())?;
+        }
+        writer.write_all(&[0, 0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a localization resource in the format of the given version
+    fn write_version(locres: &LocRes, version: u8) -> Vec<u8> {
+        if version == LOCRES_VERSION_COMPACT {
+            return locres.write().unwrap();
+        }
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut strings: Vec<&str> = Vec::new();
+
+        if version > 0 {
+            for part in LOCRES_MAGIC {
+                cursor.write_all(&part.to_le_bytes()).unwrap();
+            }
+            cursor.write_all(&[version]).unwrap();
+            cursor.write_all(&0i64.to_le_bytes()).unwrap();
+
+            let entry_count: usize = locres.entries.values().map(|e| e.len()).sum();
+            cursor
+                .write_all(&(entry_count as u32).to_le_bytes())
+                .unwrap();
+        }
+
+        cursor
+            .write_all(&(locres.entries.len() as u32).to_le_bytes())
+            .unwrap();
+        for (namespace, keys) in &locres.entries {
+            if version > 0 {
+                cursor
+                    .write_all(&str_crc32(namespace).to_le_bytes())
+                    .unwrap();
+            }
+            write_fstring(&mut cursor, namespace).unwrap();
+            cursor
+                .write_all(&(keys.len() as u32).to_le_bytes())
+                .unwrap();
+
+            for (key, entry) in keys {
+                if version > 0 {
+                    cursor.write_all(&str_crc32(key).to_le_bytes()).unwrap();
+                }
+                write_fstring(&mut cursor, key).unwrap();
+                cursor
+                    .write_all(&entry.source_string_hash.to_le_bytes())
+                    .unwrap();
+
+                if version > 0 {
+                    strings.push(&entry.localized_string);
+                    cursor
+                        .write_all(&(strings.len() as i32 - 1).to_le_bytes())
+                        .unwrap();
+                } else {
+                    write_fstring(&mut cursor, &entry.localized_string).unwrap();
+                }
+            }
+        }
+
+        if version > 0 {
+            let strings_offset = cursor.position();
+            cursor
+                .write_all(&(strings.len() as i32).to_le_bytes())
+                .unwrap();
+            for string in strings {
+                write_fstring(&mut cursor, string).unwrap();
+                cursor.write_all(&1i32.to_le_bytes()).unwrap();
+            }
+
+            cursor.set_position(LOCRES_MAGIC.len() as u64 * 4 + 1);
+            cursor
+                .write_all(&(strings_offset as i64).to_le_bytes())
+                .unwrap();
+        }
+
+        cursor.into_inner()
+    }
+
+    fn sample() -> LocRes {
+        let mut locres = LocRes::default();
+        locres.insert("Items", "Name", "Hello", "Bonjour");
+        locres.insert("Items", "Description", "Résumé", "Résumé");
+        locres.insert("Items", "Duplicate", "Hello", "Bonjour");
+        locres.insert("", "Empty", "", "");
+        locres.insert("Missions", "Title", "Rocket", "🚀");
+        locres
+    }
+
+    #[test]
+    fn round_trip_all_versions() {
+        let locres = sample();
+
+        for version in 0..=LOCRES_VERSION_LATEST {
+            let read = LocRes::read(&write_version(&locres, version)).unwrap();
+            assert_eq!(read.entries, locres.entries, "version {version}");
+
+            let rewritten = LocRes::read(&read.write().unwrap()).unwrap();
+            assert_eq!(rewritten.entries, locres.entries, "version {version}");
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut data = write_version(&sample(), LOCRES_VERSION_LATEST);
+        data[LOCRES_MAGIC.len() * 4] = LOCRES_VERSION_LATEST + 1;
+        assert!(LocRes::read(&data).is_err());
+    }
+
+    #[test]
+    fn str_crc32_matches_engine() {
+        assert_eq!(str_crc32(""), 0);
+        assert_eq!(str_crc32("Hello"), 0x2DB7CE90);
+        assert_eq!(str_crc32("Astroneer"), 0xCC36B3EB);
+        assert_eq!(str_crc32("Résumé"), 0x35DA94B5);
+        // characters outside the BMP are hashed as two UTF-16 code units
+        assert_eq!(str_crc32("🚀"), 0x89887AE6);
+    }
+}